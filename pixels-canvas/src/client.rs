@@ -3,82 +3,207 @@ use ureq::serde_json::{
     json
 };
 
-use std::io::Read;
+use std::io::{
+    ErrorKind,
+    Read
+};
+use std::str::FromStr;
+use std::time::Duration;
+
+use ureq::{
+    Agent,
+    AgentBuilder,
+    Response
+};
 
 use pixels_util::prelude::*;
 
 use crate::{
+    error::CanvasError,
     token,
     url,
 };
 
+pub const DEFAULT_URL: &str = "https://pixels.yazilimcilarinmolayeri.com";
+
+#[derive(Clone, Debug)]
+pub struct ClientConfig {
+    pub url: String,
+    pub user_agent: String,
+    pub timeout: Duration,
+    pub connect_timeout: Duration,
+}
 
 pub struct Client {
+    agent: Agent,
+    config: ClientConfig,
+    refresh: String,
     token: String
 }
 
-pub type ClientError = ureq::Error;
+impl ClientConfig {
+    pub fn new(url: String) -> Self {
+        Self {
+            url: String::from(url.trim_end_matches('/')),
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            url: String::from(DEFAULT_URL),
+            user_agent: format!("pixels-client/{}", env!("CARGO_PKG_VERSION")),
+            timeout: Duration::from_secs(30),
+            connect_timeout: Duration::from_secs(10),
+        }
+    }
+}
 
 impl Client {
-    pub fn new() -> Self {
+    pub fn new(config: ClientConfig) -> Self {
         Client {
+            agent: AgentBuilder::new()
+                .user_agent(&config.user_agent)
+                .timeout_read(config.timeout)
+                .timeout_write(config.timeout)
+                .timeout_connect(config.connect_timeout)
+                .build(),
+            config,
+            refresh: String::new(),
             token: String::new()
         }
     }
 
-    pub fn auth(&mut self, refresh: String) -> Result<(), ClientError> {
-        let body: serde_json::Value = ureq::post(url!("authenticate"))
+    pub fn config(&self) -> &ClientConfig {
+        &self.config
+    }
+
+    pub fn auth(&mut self, refresh: String) -> Result<(), CanvasError> {
+        self.refresh = refresh;
+        self.reauth()
+    }
+
+    fn reauth(&mut self) -> Result<(), CanvasError> {
+        let res = self.agent.post(url!(self.config, "authenticate"))
             .send_json(json!({
-                "refresh_token": refresh,
-            }))?.into_json()?;
-        self.token = String::from(body["access_token"].as_str().unwrap());
+                "refresh_token": self.refresh,
+            }))
+            .map_err(|e| match CanvasError::from(e) {
+                CanvasError::AuthExpired | CanvasError::Status(403, _) => CanvasError::RefreshRevoked,
+                e => e,
+            })?;
+        let body = json_body(res)?;
+        self.token = String::from(field(body["access_token"].as_str(), "access_token")?);
         Ok(())
     }
 
-    pub fn canvas_size(&self) -> Result<(u32, u32), ClientError> {
-        let body: serde_json::Value = ureq::get(url!("canvas/size"))
-            .call()?.into_json()?;
+    fn authorized<T, F>(&mut self, request: F) -> Result<T, CanvasError>
+    where
+        F: Fn(&Self) -> Result<T, CanvasError>
+    {
+        match request(self) {
+            Err(CanvasError::AuthExpired) => {
+                self.reauth()?;
+                request(self)
+            }
+            result => result,
+        }
+    }
+
+    pub fn canvas_size(&self) -> Result<(u32, u32), CanvasError> {
+        let body = json_body(self.agent.get(url!(self.config, "canvas/size"))
+            .call()?)?;
         Ok((
-            body["width"].as_u64().unwrap() as u32,
-            body["height"].as_u64().unwrap() as u32,
+            field(body["width"].as_u64(), "width")? as u32,
+            field(body["height"].as_u64(), "height")? as u32,
         ))
     }
 
-    pub fn canvas_pixels(&self) -> Result<Vec<u8>, ClientError> {
-        let mut buffer: Vec<u8> = vec![];
-        ureq::get(url!("canvas/pixels"))
-            .set(
-                "Authorization",
-                token!(self.token),
-            )
-            .call()?
-            .into_reader()
-            .read_to_end(&mut buffer)?;
-        Ok(buffer)
+    /// Downloads the rgb pixels, the response has to cover the whole canvas of the given size
+    pub fn canvas_pixels(&mut self, size: (u32, u32)) -> Result<Vec<u8>, CanvasError> {
+        let length = size.0 as usize * size.1 as usize * 3;
+
+        self.authorized(|client| {
+            let mut buffer: Vec<u8> = vec![];
+            client.agent.get(url!(client.config, "canvas/pixels"))
+                .set(
+                    "Authorization",
+                    token!(client.token),
+                )
+                .call()?
+                .into_reader()
+                .read_to_end(&mut buffer)?;
+
+            if buffer.len() != length {
+                return Err(CanvasError::Malformed(format!(
+                    "expected {length} bytes of pixels, got {}", buffer.len()
+                )))
+            }
+            Ok(buffer)
+        })
     }
 
-    pub fn canvas_set_pixel(&self, x: u32, y: u32, color: Color) -> Result<(u32, f32), ClientError> {
-        let res = ureq::put(url!("canvas/pixel"))
-            .set(
-                "Authorization",
-                token!(self.token),
-            )
-            .send_json(json!({
-                "x": x,
-                "y": y,
-                "rgb": color.to_hex(ColorMode::RGB),
-            }))?;
-        Ok((
-            res.header("requests-remaining").unwrap().parse().unwrap(),
-            res.header("requests-reset").unwrap().parse().unwrap(),
-        ))
+    pub fn canvas_stream(&mut self) -> Result<Box<dyn Read + Send + Sync>, CanvasError> {
+        self.authorized(|client| {
+            Ok(client.agent.get(url!(client.config, "canvas/stream"))
+                .set(
+                    "Authorization",
+                    token!(client.token),
+                )
+                .set("Accept", "text/event-stream")
+                .call()?
+                .into_reader())
+        })
+    }
+
+    pub fn canvas_set_pixel(&mut self, x: u32, y: u32, color: Color) -> Result<(u32, f32), CanvasError> {
+        self.authorized(|client| {
+            let res = client.agent.put(url!(client.config, "canvas/pixel"))
+                .set(
+                    "Authorization",
+                    token!(client.token),
+                )
+                .send_json(json!({
+                    "x": x,
+                    "y": y,
+                    "rgb": color.to_hex(ColorMode::RGB),
+                }))?;
+            Ok((
+                header(&res, "requests-remaining")?,
+                header(&res, "requests-reset")?,
+            ))
+        })
     }
 }
 
+fn json_body(res: Response) -> Result<serde_json::Value, CanvasError> {
+    res.into_json().map_err(|e| match e.kind() {
+        ErrorKind::InvalidData => CanvasError::Malformed(e.to_string()),
+        _ => CanvasError::Io(e),
+    })
+}
+
+fn field<T>(value: Option<T>, name: &str) -> Result<T, CanvasError> {
+    value.ok_or_else(|| CanvasError::Malformed(
+        format!("missing or invalid field {name}")
+    ))
+}
+
+fn header<T: FromStr>(res: &Response, name: &'static str) -> Result<T, CanvasError> {
+    res.header(name)
+        .ok_or(CanvasError::MissingHeader(name))?
+        .parse()
+        .map_err(|_| CanvasError::Malformed(
+            format!("invalid value for header {name}")
+        ))
+}
+
 #[macro_export]
 macro_rules! url {
-    ($path:expr) => {
-        format!("https://pixels.yazilimcilarinmolayeri.com/{}", $path).as_str()
+    ($config:expr, $path:expr) => {
+        format!("{}/{}", $config.url, $path).as_str()
     };
 }
 
@@ -0,0 +1,61 @@
+use pixels_util::prelude::*;
+
+use crate::prelude::*;
+
+#[derive(Clone, Default)]
+pub struct Diff {
+    pub matching: usize,
+    pub mismatching: usize,
+    pub transparent: usize,
+    pub wrong: Vec<(u32, u32, Color)>,
+}
+
+impl Diff {
+    /// Compares the positioned element with the layer, pixels out of the layer are ignored
+    pub fn new(element: &Element, layer: &Layer) -> Self {
+        let (px, py) = element.get_position();
+        let mut diff = Self::default();
+
+        for ((x, y), color) in element.iter() {
+            let (x, y) = (x + px, y + py);
+            let Some(current) = layer.get_pixel(x, y) else {
+                continue
+            };
+
+            if is_transparent(color) {
+                diff.transparent += 1;
+            } else if is_same(current, color) {
+                diff.matching += 1;
+            } else {
+                diff.mismatching += 1;
+                diff.wrong.push((x, y, color));
+            }
+        }
+
+        diff
+    }
+
+    pub fn total(&self) -> usize {
+        self.matching + self.mismatching
+    }
+
+    pub fn completion(&self) -> f32 {
+        if self.total() == 0 {
+            return 100.0
+        }
+        self.matching as f32 / self.total() as f32 * 100.0
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.mismatching == 0
+    }
+}
+
+/// Pixels below half alpha are neither placed nor drawn from templates
+pub(crate) fn is_transparent(color: Color) -> bool {
+    color.a < 0.5
+}
+
+pub(crate) fn is_same(a: Color, b: Color) -> bool {
+    a.to_rgb() == b.to_rgb()
+}
@@ -1,4 +1,5 @@
 use std::path::Path;
+use image::ImageResult;
 use pixels_util::pixels::PixelsIterator;
 
 use pixels_util::prelude::*;
@@ -10,13 +11,25 @@ pub struct Element {
 }
 
 impl Element {
-    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+    pub fn new<P: AsRef<Path>>(path: P) -> ImageResult<Self> {
+        Ok(Self::from_pixels(Pixels::from_path(path)?))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> ImageResult<Self> {
+        Ok(Self::from_pixels(Pixels::from_bytes(bytes)?))
+    }
+
+    pub fn from_pixels(pixels: Pixels) -> Self {
         Self {
-            pixels: Pixels::from_path(path),
+            pixels,
             position: (0, 0),
         }
     }
 
+    pub fn quantize(&mut self, palette: &Palette, dither: Dither) {
+        self.pixels = palette.quantize(&self.pixels, dither)
+    }
+
     pub fn set_position(&mut self, x: u32, y: u32) {
         self.position = (x, y)
     }
@@ -25,7 +38,7 @@ impl Element {
         self.position
     }
 
-    pub fn iter(&self) -> PixelsIterator {
+    pub fn iter(&self) -> PixelsIterator<'_> {
         self.pixels.iter()
     }
 }
@@ -1,11 +1,69 @@
+use std::error::Error;
+use std::fmt::{
+    self,
+    Display,
+    Formatter
+};
+use std::io;
+
 #[derive(Debug)]
 pub enum CanvasError {
-    Client(ureq::Error),
+    Transport(Box<ureq::Transport>),
+    Io(io::Error),
+    Status(u16, String),
+    Malformed(String),
+    MissingHeader(&'static str),
+    AuthExpired,
+    RefreshRevoked,
+    RateLimited(f32),
     Cooldown(f32),
 }
 
+impl Display for CanvasError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Transport(e) => write!(f, "transport error: {e}"),
+            Self::Io(e) => write!(f, "io error: {e}"),
+            Self::Status(code, body) => write!(f, "server responded with status {code}: {body}"),
+            Self::Malformed(reason) => write!(f, "malformed response: {reason}"),
+            Self::MissingHeader(name) => write!(f, "response is missing the {name} header"),
+            Self::AuthExpired => write!(f, "access token expired"),
+            Self::RefreshRevoked => write!(f, "refresh token is invalid or revoked"),
+            Self::RateLimited(reset) => write!(f, "rate limited for {reset} secs"),
+            Self::Cooldown(remaining) => write!(f, "cooldown ends in {remaining} secs"),
+        }
+    }
+}
+
+impl Error for CanvasError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Transport(e) => Some(e.as_ref()),
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
 impl From<ureq::Error> for CanvasError {
     fn from(value: ureq::Error) -> Self {
-        Self::Client(value)
+        match value {
+            ureq::Error::Status(401, _) => Self::AuthExpired,
+            ureq::Error::Status(429, res) => Self::RateLimited(
+                res.header("requests-reset")
+                    .and_then(|reset| reset.parse().ok())
+                    .unwrap_or_default()
+            ),
+            ureq::Error::Status(code, res) => Self::Status(
+                code, res.into_string().unwrap_or_default()
+            ),
+            ureq::Error::Transport(e) => Self::Transport(Box::new(e)),
+        }
+    }
+}
+
+impl From<io::Error> for CanvasError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
     }
 }
@@ -0,0 +1,22 @@
+use pixels_util::prelude::*;
+
+/// What `Canvas::export` renders
+#[derive(Clone, Debug)]
+pub struct ExportConfig {
+    /// Composite every visible layer instead of the main layer only
+    pub merged: bool,
+    /// Crop to this region, the whole canvas otherwise
+    pub region: Option<Region>,
+    /// Integer upscale factor, every pixel becomes a scale sized square
+    pub scale: u32,
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        Self {
+            merged: false,
+            region: None,
+            scale: 1,
+        }
+    }
+}
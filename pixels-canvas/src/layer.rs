@@ -3,20 +3,21 @@ use pixels_util::prelude::*;
 use crate::prelude::Element;
 
 pub struct Layer {
+    name: String,
     pixels: Pixels,
-    opacity: f32
+    opacity: f32,
+    visible: bool,
+    blend: BlendMode,
 }
 
 impl Layer {
-    pub fn new(size: (u32, u32), opacity: f32) -> Layer {
-        Self {
-            pixels: Pixels::new(size),
-            opacity,
-        }
+    pub fn new(name: &str, size: (u32, u32), opacity: f32) -> Layer {
+        Self::from_pixels(name, Pixels::new(size), opacity)
     }
 
-    pub fn from_vec(size: (u32, u32), buffer: Vec<u8>, opacity: f32) -> Self {
+    pub fn from_vec(name: &str, size: (u32, u32), buffer: Vec<u8>, opacity: f32) -> Self {
         Self::from_pixels(
+            name,
             Pixels::from_buffer(
                 size, buffer, ColorMode::RGB
             ),
@@ -24,15 +25,70 @@ impl Layer {
         )
     }
 
-    pub fn from_pixels(pixels: Pixels, opacity: f32) -> Self {
+    pub fn from_pixels(name: &str, pixels: Pixels, opacity: f32) -> Self {
         Self {
+            name: name.to_string(),
             pixels,
             opacity,
+            visible: true,
+            blend: BlendMode::Normal,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    pub fn set_opacity(&mut self, opacity: f32) {
+        let opacity = opacity.clamp(0.0, 1.0);
+        if self.opacity != opacity {
+            self.opacity = opacity;
+            self.mark_all();
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        if self.visible != visible {
+            self.visible = visible;
+            self.mark_all();
+        }
+    }
+
+    pub fn get_blend(&self) -> BlendMode {
+        self.blend
+    }
+
+    pub fn set_blend(&mut self, blend: BlendMode) {
+        if self.blend != blend {
+            self.blend = blend;
+            self.mark_all();
         }
     }
 
-    pub fn get_opacity(&self) -> u8 {
-        (self.opacity * 255.0) as u8
+    pub fn pixels(&self) -> &Pixels {
+        &self.pixels
+    }
+
+    pub fn into_pixels(self) -> Pixels {
+        self.pixels
+    }
+
+    /// Differs whenever the pixels may have changed
+    pub fn revision(&self) -> u64 {
+        self.pixels.revision()
+    }
+
+    /// Returns the regions changed since the last call
+    pub fn take_dirty(&mut self) -> Vec<Region> {
+        self.pixels.take_dirty()
     }
 
     pub fn get_pixel(&self, x: u32, y: u32) -> Option<Color> {
@@ -43,8 +99,9 @@ impl Layer {
         self.pixels.set(x, y, color)
     }
 
-    pub fn set_pixels(&mut self, pixels: Pixels) {
-        self.pixels = pixels
+    /// Replaces the pixels and returns the count of changed ones
+    pub fn set_pixels(&mut self, pixels: Pixels) -> usize {
+        self.pixels.replace(&pixels)
     }
 
     pub fn draw(&mut self, element: Element) {
@@ -54,20 +111,28 @@ impl Layer {
         }
     }
 
+    /// Blends other over this layer, hidden layers leave it untouched
     pub fn overlay(&self, other: &Layer) -> Layer {
-        Self::from_pixels(
-            self.pixels.overlay(
-                &other.pixels, other.opacity
-            ),
-            self.opacity
-        )
+        let pixels = if other.visible {
+            self.pixels.blend(
+                &other.pixels, other.opacity, other.blend
+            )
+        } else {
+            self.pixels.clone()
+        };
+
+        Self::from_pixels(&self.name, pixels, self.opacity)
     }
 
     pub fn clean(&mut self) {
-        self.pixels = Pixels::new(self.pixels.size())
+        self.pixels.clear()
     }
 
-    pub fn iter(&self) -> PixelsIterator {
+    pub fn iter(&self) -> PixelsIterator<'_> {
         self.pixels.iter()
     }
+
+    fn mark_all(&mut self) {
+        self.pixels.mark(Region::from_size(self.pixels.size()))
+    }
 }
@@ -1,36 +1,79 @@
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
 use paste::item;
 
+use pixels_util::pixels::MAX_DIRTY;
+use diff::is_same;
+use queue::RETRY_DELAY;
 use pixels_util::prelude::*;
 use prelude::*;
 
 mod client;
+mod diff;
 mod layer;
 mod error;
+mod export;
 mod elem;
+mod placer;
+mod queue;
+mod recorder;
+pub mod shape;
+mod stream;
+mod template;
 
 pub mod prelude {
     pub use crate::{
         Canvas,
+        MAIN_LAYER,
+        IMAGE_LAYER,
         layer::{
             Layer,
 
         },
         elem::Element,
-        client::Client,
+        diff::Diff,
+        client::{
+            Client,
+            ClientConfig,
+        },
         error::CanvasError,
+        export::ExportConfig,
+        placer::Placer,
+        queue::Queue,
+        recorder::{
+            Recorder,
+            Recording,
+        },
+        stream::{
+            PixelUpdate,
+            Stream,
+        },
+        template::{
+            Template,
+            Templates,
+        },
     };
 }
 
+pub const MAIN_LAYER: &str = "main";
+pub const IMAGE_LAYER: &str = "image";
+
 pub struct Canvas {
     client: Client,
     layers: Vec<Layer>,
+    dirty: Vec<Region>,
+    stream: Option<Stream>,
+    recorder: Option<Recorder>,
+    queue: Queue,
     cooldown: Cooldown,
     size: (u32, u32),
 }
 
 impl Canvas {
-    pub fn new(refresh: String) -> Result<Self, CanvasError> {
-        let mut client = Client::new();
+    pub fn new(config: ClientConfig, refresh: String) -> Result<Self, CanvasError> {
+        let mut client = Client::new(config);
 
         client.auth(refresh)?;
 
@@ -38,12 +81,19 @@ impl Canvas {
             size: client.canvas_size()?,
             cooldown: Cooldown::new(),
             layers: vec![],
+            dirty: vec![],
+            stream: None,
+            recorder: None,
+            queue: Queue::new(),
             client,
         };
 
-        canvas.add_layer(Layer::new(canvas.size(), 1.0));
-        canvas.add_layer(Layer::new(canvas.size(), 0.5));
+        canvas.add_layer(MAIN_LAYER, 1.0);
+        canvas.add_layer(IMAGE_LAYER, 0.5);
 
+        // the stream opens first so nothing placed before the download finishes is missed,
+        // when it can't be opened subscribe reports why
+        canvas.stream = canvas.client.canvas_stream().ok().map(Stream::new);
         canvas.update_main_layer()?;
 
         Ok(canvas)
@@ -65,49 +115,216 @@ impl Canvas {
         self.cooldown.remaining()
     }
 
-    fn add_layer(&mut self, layer: Layer) {
-        self.layers.push(layer)
+    /// Pushes an empty layer on top of the stack, names are unique
+    pub fn add_layer(&mut self, name: &str, opacity: f32) -> Option<&mut Layer> {
+        if self.layer_index(name).is_some() {
+            return None
+        }
+
+        self.layers.push(Layer::new(name, self.size, opacity));
+        self.layers.last_mut()
+    }
+
+    /// Removes a layer from the stack, the main and image layers can't be removed
+    pub fn remove_layer(&mut self, name: &str) -> Option<Layer> {
+        if name == MAIN_LAYER || name == IMAGE_LAYER {
+            return None
+        }
+
+        let layer = self.layers.remove(self.layer_index(name)?);
+        self.mark_all();
+        Some(layer)
+    }
+
+    /// Moves a layer to the given position, zero being the bottom of the stack
+    pub fn move_layer(&mut self, name: &str, index: usize) -> bool {
+        let Some(from) = self.layer_index(name) else {
+            return false
+        };
+
+        let layer = self.layers.remove(from);
+        self.layers.insert(index.min(self.layers.len()), layer);
+        self.mark_all();
+        true
+    }
+
+    pub fn layer_index(&self, name: &str) -> Option<usize> {
+        self.layers.iter().position(|layer| layer.name() == name)
+    }
+
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    pub fn layers_mut(&mut self) -> &mut [Layer] {
+        &mut self.layers
     }
 
-    pub fn get_layer(&self, id: usize) -> Option<&Layer> {
-        self.layers.get(id)
+    pub fn get_layer(&self, name: &str) -> Option<&Layer> {
+        self.layers.iter().find(|layer| layer.name() == name)
     }
 
-    pub fn get_layer_mut(&mut self, id: usize) -> Option<&mut Layer> {
-        self.layers.get_mut(id)
+    pub fn get_layer_mut(&mut self, name: &str) -> Option<&mut Layer> {
+        self.layers.iter_mut().find(|layer| layer.name() == name)
     }
 
-    layer_accessors!(main, 0);
-    layer_accessors!(image, 1);
+    layer_accessors!(main, MAIN_LAYER);
+    layer_accessors!(image, IMAGE_LAYER);
 
     pub fn get_layers_merged(&self) -> Layer {
         self.layers.iter().fold(
-            Layer::new(self.size, 1.0),
+            Layer::new(MAIN_LAYER, self.size, 1.0),
             |l, o| l.overlay(o),
         )
     }
 
-    pub fn update_main_layer(&mut self) -> Result<(), CanvasError> {
-        let pixels = self.client.canvas_pixels()?;
+    /// Composites the visible layers inside the region into pixels of the region's size
+    pub fn merge_region(&self, region: Region) -> Pixels {
+        self.merge_layers_region(self.layers.len(), region)
+    }
+
+    /// Composites the visible layers among the bottom `count` inside the region
+    pub fn merge_layers_region(&self, count: usize, region: Region) -> Pixels {
+        let mut pixels = Pixels::new((region.width, region.height));
+        let layers: Vec<&Layer> = self.layers
+            .iter()
+            .take(count)
+            .filter(|layer| layer.is_visible())
+            .collect();
+
+        for y in 0..region.height {
+            for x in 0..region.width {
+                let color = layers.iter().fold(Color::default(), |color, layer| {
+                    match layer.get_pixel(region.x + x, region.y + y) {
+                        Some(other) => color.blend(other, layer.get_opacity(), layer.get_blend()),
+                        None => color,
+                    }
+                });
+                pixels.set(x, y, color);
+            }
+        }
+
+        pixels
+    }
+
+    pub fn export(&self, config: &ExportConfig) -> Pixels {
+        let bounds = Region::from_size(self.size);
+        let region = config.region
+            .map(|region| region.intersection(&bounds).unwrap_or_default())
+            .unwrap_or(bounds);
+        let pixels = if config.merged {
+            self.merge_region(region)
+        } else {
+            self.get_main_layer().pixels().crop(region)
+        };
+
+        pixels.upscale(config.scale)
+    }
+
+    /// Returns the regions where the composited layers changed since the last call
+    pub fn take_dirty(&mut self) -> Vec<Region> {
+        let mut dirty = std::mem::take(&mut self.dirty);
+        for layer in self.layers.iter_mut() {
+            for region in layer.take_dirty() {
+                region.merge_into(&mut dirty, MAX_DIRTY);
+            }
+        }
+        dirty
+    }
+
+    /// Downloads the canvas and returns the count of pixels that changed
+    pub fn update_main_layer(&mut self) -> Result<usize, CanvasError> {
         let size = self.size;
+        let pixels = self.client.canvas_pixels(size)?;
 
-        self.get_main_layer_mut().set_pixels(
+        let changed = self.get_main_layer_mut().set_pixels(
             Pixels::from_buffer(
                 size,
                 pixels,
                 ColorMode::RGB,
             )
         );
+        self.record()?;
+
+        Ok(changed)
+    }
+
+    /// Records the main layer inside the region, or the whole canvas, every interval
+    pub fn start_recording<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        region: Option<Region>,
+        interval: Duration,
+    ) -> Result<(), CanvasError> {
+        let bounds = Region::from_size(self.size);
+        let region = region
+            .map(|region| region.intersection(&bounds).unwrap_or_default())
+            .unwrap_or(bounds);
+
+        self.recorder = Some(Recorder::new(path, region, interval)?);
+        self.record()?;
+        Ok(())
+    }
+
+    pub fn stop_recording(&mut self) -> Option<Recorder> {
+        self.recorder.take()
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    /// Opens the live stream unless it's open, then downloads the canvas to catch up
+    pub fn subscribe(&mut self) -> Result<(), CanvasError> {
+        if self.stream.is_some() {
+            return Ok(())
+        }
+
+        self.stream = Some(Stream::new(self.client.canvas_stream()?));
+        self.update_main_layer()?;
         Ok(())
     }
 
+    pub fn is_subscribed(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    pub fn update_from_stream(&mut self) -> bool {
+        let Some(stream) = self.stream.as_mut() else {
+            return false
+        };
+
+        let updates = stream.poll();
+        if stream.is_closed() {
+            self.stream = None;
+        }
+
+        let layer = self.get_main_layer_mut();
+        for update in updates {
+            layer.set_pixel(update.x, update.y, update.color);
+        }
+
+        if self.record().is_err() {
+            self.recorder = None;
+        }
+        self.is_subscribed()
+    }
+
     pub fn set_pixel(&mut self, x: u32, y: u32, color: Color) -> Result<(), CanvasError> {
         if !self.cooldown.is_ended() {
             return Err(CanvasError::Cooldown(self.get_cooldown()))
         }
 
+        let (remain, delay) = match self.client.canvas_set_pixel(x, y, color) {
+            Err(CanvasError::RateLimited(delay)) => {
+                // a response without a reset header would otherwise retry right away
+                let delay = if delay > 0.0 { delay } else { RETRY_DELAY.as_secs_f32() };
+                self.cooldown.set(delay);
+                return Err(CanvasError::Cooldown(delay))
+            }
+            result => result?,
+        };
         self.get_main_layer_mut().set_pixel(x, y, color);
-        let (remain, delay) = self.client.canvas_set_pixel(x, y, color)?;
 
         if remain == 0 {
             self.cooldown.set(delay)
@@ -116,21 +333,80 @@ impl Canvas {
         Ok(())
     }
 
+    pub fn queue(&self) -> &Queue {
+        &self.queue
+    }
+
+    pub fn queue_mut(&mut self) -> &mut Queue {
+        &mut self.queue
+    }
+
+    /// Places the next queued pixel once the cooldown ended. Pixels that already have their
+    /// color are skipped, ones the server rejects are dropped and other failures pause the queue
+    pub fn place_queued(&mut self) -> Result<Option<(u32, u32)>, CanvasError> {
+        if !self.cooldown.is_ended() || self.queue.is_paused() {
+            return Ok(None)
+        }
+
+        while let Some((x, y, color)) = self.queue.pop_front() {
+            if self.get_pixel(x, y).is_some_and(|current| is_same(current, color)) {
+                continue
+            }
+
+            return match self.set_pixel(x, y, color) {
+                Ok(()) => Ok(Some((x, y))),
+                Err(CanvasError::Cooldown(_)) => {
+                    self.queue.push_front(x, y, color);
+                    Ok(None)
+                }
+                Err(CanvasError::Status(code, body)) if code < 500 => {
+                    Err(CanvasError::Status(code, body))
+                }
+                Err(e) => {
+                    self.queue.push_front(x, y, color);
+                    self.queue.pause();
+                    Err(e)
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
     pub fn get_pixel(&self, x: u32, y: u32) -> Option<Color> {
         self.get_main_layer().get_pixel(x, y)
     }
+
+    pub fn diff(&self, element: &Element) -> Diff {
+        Diff::new(element, self.get_main_layer())
+    }
+
+    fn record(&mut self) -> io::Result<()> {
+        let Some(recorder) = self.recorder.as_mut() else {
+            return Ok(())
+        };
+        let Some(layer) = self.layers.iter().find(|layer| layer.name() == MAIN_LAYER) else {
+            return Ok(())
+        };
+
+        recorder.capture(layer.pixels()).map(|_| ())
+    }
+
+    fn mark_all(&mut self) {
+        self.dirty = vec![Region::from_size(self.size)];
+    }
 }
 
 #[macro_export]
 macro_rules! layer_accessors {
-    ($name:ident, $id:expr) => {
+    ($name:ident, $layer:expr) => {
         item! {
             pub fn [<get_ $name _layer>](&self) -> &Layer {
-                self.layers.get($id).unwrap()
+                self.get_layer($layer).unwrap()
             }
 
             pub fn [<get_ $name _layer_mut>](&mut self) -> &mut Layer {
-                self.layers.get_mut($id).unwrap()
+                self.get_layer_mut($layer).unwrap()
             }
         }
     };
@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+
+use pixels_util::prelude::*;
+
+use crate::prelude::*;
+use crate::diff::{
+    is_same,
+    is_transparent
+};
+
+/// Places an element through the canvas queue, the wrong pixels are queued whenever it runs empty
+pub struct Placer {
+    element: Element,
+}
+
+impl Placer {
+    pub fn new(element: Element) -> Self {
+        Self {
+            element,
+        }
+    }
+
+    pub fn element(&self) -> &Element {
+        &self.element
+    }
+
+    /// Queues every pixel of the element that differs from the canvas
+    pub fn refresh(&self, canvas: &mut Canvas) {
+        let wrong = canvas.diff(&self.element).wrong;
+        canvas.queue_mut().extend(wrong);
+    }
+
+    /// Refills the queue once it ran empty and places the next queued pixel
+    pub fn tick(&self, canvas: &mut Canvas) -> Result<Option<(u32, u32)>, CanvasError> {
+        if canvas.queue().is_empty() {
+            self.refresh(canvas);
+        }
+
+        canvas.place_queued()
+    }
+
+    /// Drops the queued pixels that belong to the element
+    pub fn cancel(&self, canvas: &mut Canvas) {
+        let (px, py) = self.element.get_position();
+        let pixels: HashMap<(u32, u32), Color> = self.element
+            .iter()
+            .filter(|(_, color)| !is_transparent(*color))
+            .map(|((x, y), color)| ((x + px, y + py), color))
+            .collect();
+
+        canvas.queue_mut().retain(|(x, y, color)| {
+            !pixels.get(&(*x, *y)).is_some_and(|expected| is_same(*expected, *color))
+        });
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::{
+    HashMap,
+    VecDeque
+};
+use std::fs;
+use std::io::{
+    self,
+    ErrorKind
+};
+use std::path::Path;
+use std::time::{
+    Duration,
+    Instant
+};
+
+use serde::{
+    Deserialize,
+    Serialize
+};
+use ureq::serde_json;
+
+use pixels_util::prelude::*;
+
+pub(crate) const RETRY_DELAY: Duration = Duration::from_secs(5);
+/// Cancelled entries are dropped from the order once they outnumber the queued ones by this much
+const MAX_STALE: usize = 1024;
+
+/// Pixels waiting to be placed, the canvas places them in order as the cooldown allows.
+/// Every entry gets an id, cancelled entries stay in the order until they're skipped or compacted
+#[derive(Default)]
+pub struct Queue {
+    entries: VecDeque<(u64, (u32, u32, Color))>,
+    live: HashMap<(u32, u32), u64>,
+    next: u64,
+    revision: u64,
+    paused: Option<Instant>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedPixel {
+    x: u32,
+    y: u32,
+    color: String,
+}
+
+impl Queue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let saved: Vec<SavedPixel> = serde_json::from_str(&text)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+
+        let mut queue = Self::new();
+        queue.extend(saved
+            .into_iter()
+            .filter_map(|saved| Some((saved.x, saved.y, Color::from_hex(&saved.color, ColorMode::RGB)?))));
+        Ok(queue)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        if let Some(parent) = path.as_ref().parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let saved: Vec<SavedPixel> = self
+            .iter()
+            .map(|(x, y, color)| SavedPixel {
+                x: *x,
+                y: *y,
+                color: color.to_hex(ColorMode::RGB),
+            })
+            .collect();
+        let text = serde_json::to_string(&saved)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        fs::write(path, text)
+    }
+
+    /// Appends the pixel, an entry queued earlier for the same position is replaced
+    pub fn push(&mut self, x: u32, y: u32, color: Color) {
+        let id = self.insert(x, y);
+        self.entries.push_back((id, (x, y, color)));
+    }
+
+    /// Queues the pixel ahead of every other entry
+    pub fn push_front(&mut self, x: u32, y: u32, color: Color) {
+        let id = self.insert(x, y);
+        self.entries.push_front((id, (x, y, color)));
+    }
+
+    /// Appends every pixel in order, like pushing them one by one
+    pub fn extend<I: IntoIterator<Item = (u32, u32, Color)>>(&mut self, pixels: I) {
+        for (x, y, color) in pixels {
+            self.push(x, y, color);
+        }
+    }
+
+    pub fn pop_front(&mut self) -> Option<(u32, u32, Color)> {
+        while let Some((id, (x, y, color))) = self.entries.pop_front() {
+            if self.live.get(&(x, y)) == Some(&id) {
+                self.live.remove(&(x, y));
+                self.revision += 1;
+                return Some((x, y, color))
+            }
+        }
+        None
+    }
+
+    /// Drops the entry for the position, returns whether there was one
+    pub fn cancel(&mut self, x: u32, y: u32) -> bool {
+        let cancelled = self.live.remove(&(x, y)).is_some();
+        if cancelled {
+            self.revision += 1;
+        }
+        self.compact_if_stale();
+        cancelled
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<(u32, u32, Color)> {
+        let (x, y, color) = *self.iter().nth(index)?;
+        self.cancel(x, y);
+        Some((x, y, color))
+    }
+
+    /// Moves the entry to the index, returns whether it moved
+    pub fn move_entry(&mut self, from: usize, to: usize) -> bool {
+        if from >= self.len() || to >= self.len() || from == to {
+            return false
+        }
+
+        self.compact();
+        let Some(entry) = self.entries.remove(from) else {
+            return false
+        };
+        self.entries.insert(to, entry);
+        self.revision += 1;
+        true
+    }
+
+    /// Keeps only the entries the predicate accepts
+    pub fn retain<F: FnMut(&(u32, u32, Color)) -> bool>(&mut self, mut f: F) {
+        let len = self.live.len();
+        let live = &mut self.live;
+        self.entries.retain(|(id, pixel)| {
+            let position = (pixel.0, pixel.1);
+            if live.get(&position) != Some(id) {
+                return false
+            }
+
+            let keep = f(pixel);
+            if !keep {
+                live.remove(&position);
+            }
+            keep
+        });
+
+        if self.live.len() != len {
+            self.revision += 1;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.live.clear();
+        self.revision += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.live.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.live.is_empty()
+    }
+
+    /// Differs whenever entries were added, removed or moved
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(u32, u32, Color)> {
+        self.entries
+            .iter()
+            .filter(|(id, (x, y, _))| self.live.get(&(*x, *y)) == Some(id))
+            .map(|(_, pixel)| pixel)
+    }
+
+    /// Holds placing back for a while after a failed attempt
+    pub(crate) fn pause(&mut self) {
+        self.paused = Some(Instant::now() + RETRY_DELAY);
+    }
+
+    pub(crate) fn is_paused(&self) -> bool {
+        self.paused.is_some_and(|until| Instant::now() < until)
+    }
+
+    /// Gives the position a new id, which makes any earlier entry for it stale
+    fn insert(&mut self, x: u32, y: u32) -> u64 {
+        let id = self.next;
+        self.next += 1;
+        self.revision += 1;
+        self.live.insert((x, y), id);
+        self.compact_if_stale();
+        id
+    }
+
+    fn compact_if_stale(&mut self) {
+        if self.entries.len() > self.live.len() * 2 + MAX_STALE {
+            self.compact();
+        }
+    }
+
+    /// Drops the stale entries so indices into the order match `iter`
+    fn compact(&mut self) {
+        let live = &self.live;
+        self.entries.retain(|(id, (x, y, _))| live.get(&(*x, *y)) == Some(id));
+    }
+}
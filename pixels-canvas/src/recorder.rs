@@ -0,0 +1,257 @@
+use std::fs::{
+    self,
+    File
+};
+use std::io::{
+    self,
+    BufReader,
+    BufWriter,
+    ErrorKind,
+    Read,
+    Write
+};
+use std::path::Path;
+use std::time::{
+    Duration,
+    Instant,
+    SystemTime,
+    UNIX_EPOCH
+};
+
+use flate2::Compression;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use image::{
+    Delay,
+    Frame,
+    ImageResult
+};
+use image::codecs::gif::{
+    GifEncoder,
+    Repeat
+};
+
+use pixels_util::prelude::*;
+
+const MAGIC: &[u8; 4] = b"PXTL";
+const VERSION: u8 = 1;
+const KEYFRAME: u8 = 0;
+const DELTA: u8 = 1;
+const KEYFRAME_EVERY: usize = 100;
+
+/// Writes a timelapse of a canvas region.
+///
+/// The file starts with `PXTL`, a version byte and the region as four little endian u32s.
+/// Every frame is a u64 unix timestamp in millis, a kind byte and a u32 length followed by
+/// that many bytes of zlib data. Keyframes hold the rgb bytes of the region, deltas hold
+/// `(u32 index, r, g, b)` entries for the pixels that changed since the previous frame.
+pub struct Recorder {
+    writer: BufWriter<File>,
+    region: Region,
+    interval: Duration,
+    last: Option<Instant>,
+    previous: Option<Vec<u8>>,
+    frames: usize,
+}
+
+/// Reads back the frames of a recording
+pub struct Recording {
+    reader: BufReader<File>,
+    region: Region,
+    current: Vec<u8>,
+}
+
+impl Recorder {
+    pub fn new<P: AsRef<Path>>(path: P, region: Region, interval: Duration) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        for value in [region.x, region.y, region.width, region.height] {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        writer.flush()?;
+
+        Ok(Self {
+            writer,
+            region,
+            interval,
+            last: None,
+            previous: None,
+            frames: 0,
+        })
+    }
+
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// Stores a frame once the interval passed since the last one, returns whether it did
+    pub fn capture(&mut self, pixels: &Pixels) -> io::Result<bool> {
+        if self.last.is_some_and(|last| last.elapsed() < self.interval) {
+            return Ok(false)
+        }
+        self.last = Some(Instant::now());
+
+        let current: Vec<u8> = pixels
+            .region(self.region.x, self.region.y, self.region.width, self.region.height)
+            .flatten()
+            .enumerate()
+            .filter(|(i, _)| i % 4 != 3)
+            .map(|(_, channel)| *channel)
+            .collect();
+
+        let delta = self.previous
+            .as_ref()
+            .filter(|previous| previous.len() == current.len() && !self.frames.is_multiple_of(KEYFRAME_EVERY))
+            .map(|previous| delta(previous, &current))
+            .filter(|delta| delta.len() < current.len());
+
+        match delta {
+            Some(delta) if delta.is_empty() => return Ok(false),
+            Some(delta) => self.write_frame(DELTA, &delta)?,
+            None => self.write_frame(KEYFRAME, &current)?,
+        }
+
+        self.previous = Some(current);
+        self.frames += 1;
+        Ok(true)
+    }
+
+    fn write_frame(&mut self, kind: u8, payload: &[u8]) -> io::Result<()> {
+        let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+        encoder.write_all(payload)?;
+        let data = encoder.finish()?;
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+
+        self.writer.write_all(&timestamp.to_le_bytes())?;
+        self.writer.write_all(&[kind])?;
+        self.writer.write_all(&(data.len() as u32).to_le_bytes())?;
+        self.writer.write_all(&data)?;
+        self.writer.flush()
+    }
+}
+
+impl Recording {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let mut header = [0; 5];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != MAGIC || header[4] != VERSION {
+            return Err(io::Error::new(ErrorKind::InvalidData, "not a timelapse recording"))
+        }
+
+        let region = Region::new(
+            read_u32(&mut reader)?,
+            read_u32(&mut reader)?,
+            read_u32(&mut reader)?,
+            read_u32(&mut reader)?,
+        );
+
+        Ok(Self {
+            reader,
+            region,
+            current: vec![0; region.width as usize * region.height as usize * 3],
+        })
+    }
+
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    /// Reads the next frame and returns its unix timestamp in millis with the pixels
+    pub fn next_frame(&mut self) -> io::Result<Option<(u64, Pixels)>> {
+        let mut timestamp = [0; 8];
+        match self.reader.read_exact(&mut timestamp) {
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            result => result?,
+        }
+
+        let mut kind = [0; 1];
+        self.reader.read_exact(&mut kind)?;
+        let mut data = vec![0; read_u32(&mut self.reader)? as usize];
+        self.reader.read_exact(&mut data)?;
+
+        let mut payload = vec![];
+        ZlibDecoder::new(data.as_slice()).read_to_end(&mut payload)?;
+
+        match kind[0] {
+            KEYFRAME if payload.len() == self.current.len() => self.current = payload,
+            DELTA => {
+                for entry in payload.chunks_exact(7) {
+                    let index = u32::from_le_bytes([entry[0], entry[1], entry[2], entry[3]]) as usize * 3;
+                    if let Some(pixel) = self.current.get_mut(index..index + 3) {
+                        pixel.copy_from_slice(&entry[4..7]);
+                    }
+                }
+            }
+            _ => return Err(io::Error::new(ErrorKind::InvalidData, "malformed frame")),
+        }
+
+        Ok(Some((
+            u64::from_le_bytes(timestamp),
+            Pixels::from_buffer(
+                (self.region.width, self.region.height),
+                self.current.clone(),
+                ColorMode::RGB,
+            ),
+        )))
+    }
+
+    /// Encodes every frame into an endlessly looping gif
+    pub fn render_gif<P: AsRef<Path>>(&mut self, path: P, delay: Duration, scale: u32) -> ImageResult<usize> {
+        let mut encoder = GifEncoder::new(BufWriter::new(File::create(path)?));
+        encoder.set_repeat(Repeat::Infinite)?;
+
+        let mut count = 0;
+        while let Some((_, pixels)) = self.next_frame()? {
+            encoder.encode_frame(Frame::from_parts(
+                pixels.upscale(scale).to_image(),
+                0, 0,
+                Delay::from_saturating_duration(delay),
+            ))?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Saves every frame as a numbered png inside the directory
+    pub fn render_frames<P: AsRef<Path>>(&mut self, directory: P, scale: u32) -> ImageResult<usize> {
+        fs::create_dir_all(directory.as_ref())?;
+
+        let mut count = 0;
+        while let Some((_, pixels)) = self.next_frame()? {
+            pixels
+                .upscale(scale)
+                .save(directory.as_ref().join(format!("frame-{count:05}.png")))?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+fn delta(previous: &[u8], current: &[u8]) -> Vec<u8> {
+    let mut delta = vec![];
+    for (index, (old, new)) in previous.chunks_exact(3).zip(current.chunks_exact(3)).enumerate() {
+        if old != new {
+            delta.extend_from_slice(&(index as u32).to_le_bytes());
+            delta.extend_from_slice(new);
+        }
+    }
+    delta
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut buffer = [0; 4];
+    reader.read_exact(&mut buffer)?;
+    Ok(u32::from_le_bytes(buffer))
+}
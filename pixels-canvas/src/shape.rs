@@ -0,0 +1,79 @@
+use std::collections::{
+    HashSet,
+    VecDeque
+};
+
+use crate::prelude::*;
+use crate::diff::is_same;
+
+/// Pixels on the line between both ends, including them
+pub fn line(from: (u32, u32), to: (u32, u32)) -> Vec<(u32, u32)> {
+    let (mut x, mut y) = (from.0 as i64, from.1 as i64);
+    let (end_x, end_y) = (to.0 as i64, to.1 as i64);
+    let (dx, dy) = ((end_x - x).abs(), -(end_y - y).abs());
+    let (step_x, step_y) = ((end_x - x).signum(), (end_y - y).signum());
+    let mut error = dx + dy;
+    let mut points = vec![];
+
+    loop {
+        points.push((x as u32, y as u32));
+        if x == end_x && y == end_y {
+            return points
+        }
+
+        let double = error * 2;
+        if double >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if double <= dx {
+            error += dx;
+            y += step_y;
+        }
+    }
+}
+
+/// Pixels of the rectangle spanned by two corners, only its border unless filled
+pub fn rectangle(from: (u32, u32), to: (u32, u32), filled: bool) -> Vec<(u32, u32)> {
+    let (left, right) = (from.0.min(to.0), from.0.max(to.0));
+    let (top, bottom) = (from.1.min(to.1), from.1.max(to.1));
+
+    (top..=bottom)
+        .flat_map(|y| (left..=right).map(move |x| (x, y)))
+        .filter(|(x, y)| filled || *x == left || *x == right || *y == top || *y == bottom)
+        .collect()
+}
+
+/// Pixels connected to the start that share its color, none when more than `limit` would be filled
+pub fn flood_fill(layer: &Layer, start: (u32, u32), limit: usize) -> Option<Vec<(u32, u32)>> {
+    let target = layer.get_pixel(start.0, start.1)?;
+    let mut seen = HashSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+    let mut points = vec![];
+
+    while let Some((x, y)) = queue.pop_front() {
+        points.push((x, y));
+        if points.len() > limit {
+            return None
+        }
+
+        let neighbours = [
+            x.checked_sub(1).map(|x| (x, y)),
+            Some((x + 1, y)),
+            y.checked_sub(1).map(|y| (x, y)),
+            Some((x, y + 1)),
+        ];
+
+        for point in neighbours.into_iter().flatten() {
+            let matches = layer
+                .get_pixel(point.0, point.1)
+                .is_some_and(|color| is_same(color, target));
+
+            if matches && seen.insert(point) {
+                queue.push_back(point);
+            }
+        }
+    }
+
+    Some(points)
+}
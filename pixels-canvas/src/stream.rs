@@ -0,0 +1,111 @@
+use std::io::{
+    BufRead,
+    BufReader,
+    Read
+};
+use std::sync::{
+    Arc,
+    Mutex
+};
+use std::sync::atomic::{
+    AtomicBool,
+    Ordering
+};
+use std::sync::mpsc::{
+    self,
+    Receiver,
+    TryRecvError
+};
+use std::thread;
+
+use ureq::serde_json::{
+    self,
+    Value
+};
+
+use pixels_util::prelude::*;
+
+#[derive(Copy, Clone)]
+pub struct PixelUpdate {
+    pub x: u32,
+    pub y: u32,
+    pub color: Color,
+}
+
+/// Reads pixel updates on a thread of its own, once dropped the thread closes the
+/// connection at the next line or read timeout
+pub struct Stream {
+    receiver: Mutex<Receiver<PixelUpdate>>,
+    closed: bool,
+    dropped: Arc<AtomicBool>,
+}
+
+impl Stream {
+    pub fn new<R: Read + Send + 'static>(reader: R) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let dropped = Arc::new(AtomicBool::new(false));
+        let stop = dropped.clone();
+
+        thread::spawn(move || {
+            for line in BufReader::new(reader).lines() {
+                let Ok(line) = line else {
+                    break
+                };
+                if stop.load(Ordering::Relaxed) {
+                    break
+                }
+
+                let update = line
+                    .strip_prefix("data:")
+                    .and_then(|data| parse_update(data.trim()));
+
+                if let Some(update) = update {
+                    if sender.send(update).is_err() {
+                        break
+                    }
+                }
+            }
+        });
+
+        Self {
+            receiver: Mutex::new(receiver),
+            closed: false,
+            dropped,
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    pub fn poll(&mut self) -> Vec<PixelUpdate> {
+        let receiver = self.receiver.get_mut().unwrap();
+        let mut updates = vec![];
+        loop {
+            match receiver.try_recv() {
+                Ok(update) => updates.push(update),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.closed = true;
+                    break
+                }
+            }
+        }
+        updates
+    }
+}
+
+impl Drop for Stream {
+    fn drop(&mut self) {
+        self.dropped.store(true, Ordering::Relaxed);
+    }
+}
+
+fn parse_update(data: &str) -> Option<PixelUpdate> {
+    let body: Value = serde_json::from_str(data).ok()?;
+    Some(PixelUpdate {
+        x: body["x"].as_u64()? as u32,
+        y: body["y"].as_u64()? as u32,
+        color: Color::from_hex(body["rgb"].as_str()?, ColorMode::RGB)?,
+    })
+}
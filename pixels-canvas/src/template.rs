@@ -0,0 +1,274 @@
+use std::fs;
+use std::io::{
+    self,
+    ErrorKind
+};
+use std::path::{
+    Path,
+    PathBuf
+};
+
+use image::ImageResult;
+use serde::{
+    Deserialize,
+    Serialize
+};
+use ureq::serde_json;
+
+use pixels_util::prelude::*;
+
+use crate::diff::is_transparent;
+use crate::prelude::*;
+
+/// An artwork kept on top of the canvas, only its settings are persisted
+pub struct Template {
+    name: String,
+    path: PathBuf,
+    element: Element,
+    opacity: f32,
+    visible: bool,
+    locked: bool,
+    palette: Option<String>,
+    dither: Dither,
+    revision: u64,
+}
+
+#[derive(Default)]
+pub struct Templates {
+    templates: Vec<Template>,
+    revision: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedTemplate {
+    name: String,
+    path: PathBuf,
+    position: (u32, u32),
+    opacity: f32,
+    visible: bool,
+    locked: bool,
+    #[serde(default)]
+    palette: Option<String>,
+    #[serde(default)]
+    dither: Option<String>,
+}
+
+impl Template {
+    pub fn new<P: AsRef<Path>>(path: P) -> ImageResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        Ok(Self {
+            name: path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            element: Element::new(&path)?,
+            path,
+            opacity: 1.0,
+            visible: true,
+            locked: false,
+            palette: None,
+            dither: Dither::None,
+            revision: 0,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn element(&self) -> &Element {
+        &self.element
+    }
+
+    pub fn get_palette(&self) -> Option<&str> {
+        self.palette.as_deref()
+    }
+
+    pub fn get_dither(&self) -> Dither {
+        self.dither
+    }
+
+    /// Reloads the image and quantizes it to a palette preset or file, without one it keeps its colors
+    pub fn set_palette(&mut self, palette: Option<&str>, dither: Dither) -> ImageResult<()> {
+        let mut element = Element::new(&self.path)?;
+        if let Some(name) = palette {
+            element.quantize(&Palette::load(name)?, dither);
+        }
+
+        let (x, y) = self.get_position();
+        element.set_position(x, y);
+
+        self.element = element;
+        self.palette = palette.map(String::from);
+        self.dither = dither;
+        self.revision += 1;
+        Ok(())
+    }
+
+    pub fn get_position(&self) -> (u32, u32) {
+        self.element.get_position()
+    }
+
+    /// Moves the template unless it's locked, returns whether it moved
+    pub fn set_position(&mut self, x: u32, y: u32) -> bool {
+        if self.locked {
+            return false
+        }
+        if self.element.get_position() != (x, y) {
+            self.element.set_position(x, y);
+            self.revision += 1;
+        }
+        true
+    }
+
+    pub fn get_opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    pub fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity.clamp(0.0, 1.0);
+        self.revision += 1;
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+        self.revision += 1;
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    pub fn set_locked(&mut self, locked: bool) {
+        self.locked = locked;
+        self.revision += 1;
+    }
+
+    fn save(&self) -> SavedTemplate {
+        SavedTemplate {
+            name: self.name.clone(),
+            path: self.path.clone(),
+            position: self.get_position(),
+            opacity: self.opacity,
+            visible: self.visible,
+            locked: self.locked,
+            palette: self.palette.clone(),
+            dither: Some(self.dither.name().to_string()),
+        }
+    }
+
+    fn load(saved: SavedTemplate) -> ImageResult<Self> {
+        let mut template = Self::new(&saved.path)?;
+        template.element.set_position(saved.position.0, saved.position.1);
+        template.name = saved.name;
+        template.opacity = saved.opacity;
+        template.visible = saved.visible;
+        template.locked = saved.locked;
+        template.dither = saved.dither
+            .and_then(|dither| dither.parse().ok())
+            .unwrap_or(Dither::None);
+
+        if let Some(name) = saved.palette {
+            template.element.quantize(&Palette::load(&name)?, template.dither);
+            template.palette = Some(name);
+        }
+        Ok(template)
+    }
+}
+
+impl Templates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the saved templates, entries whose image can't be loaded anymore are dropped
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let saved: Vec<SavedTemplate> = serde_json::from_str(&text)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+
+        Ok(Self {
+            templates: saved
+                .into_iter()
+                .filter_map(|saved| Template::load(saved).ok())
+                .collect(),
+            revision: 0,
+        })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        if let Some(parent) = path.as_ref().parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let saved: Vec<SavedTemplate> = self.templates.iter().map(Template::save).collect();
+        let text = serde_json::to_string_pretty(&saved)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        fs::write(path, text)
+    }
+
+    pub fn add(&mut self, template: Template) -> &mut Template {
+        self.revision += 1;
+        self.templates.push(template);
+        self.templates.last_mut().unwrap()
+    }
+
+    pub fn remove(&mut self, id: usize) -> Option<Template> {
+        let template = (id < self.templates.len()).then(|| self.templates.remove(id))?;
+        self.revision += template.revision + 1;
+        Some(template)
+    }
+
+    /// Differs whenever a template was added, removed or changed
+    pub fn revision(&self) -> u64 {
+        self.revision + self.templates.iter().map(|template| template.revision).sum::<u64>()
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Template> {
+        self.templates.get(id)
+    }
+
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut Template> {
+        self.templates.get_mut(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.templates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.templates.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Template> {
+        self.templates.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Template> {
+        self.templates.iter_mut()
+    }
+
+    /// Draws the visible templates in order, later ones cover earlier ones,
+    /// pixels the diff treats as transparent are left out
+    pub fn draw(&self, layer: &mut Layer) {
+        for template in self.templates.iter().filter(|template| template.visible) {
+            let (px, py) = template.get_position();
+            for ((x, y), color) in template.element.iter() {
+                if is_transparent(color) {
+                    continue
+                }
+                layer.set_pixel(x + px, y + py, Color::new(
+                    color.r, color.g, color.b, color.a * template.opacity
+                ));
+            }
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use pixels_canvas::prelude::*;
+use pixels_util::prelude::*;
+
+fn gray(value: u8) -> Color {
+    Color::from_rgb(value, value, value)
+}
+
+fn positions(queue: &Queue) -> Vec<(u32, u32)> {
+    queue.iter().map(|(x, y, _)| (*x, *y)).collect()
+}
+
+#[test]
+fn pops_in_order() {
+    let mut queue = Queue::new();
+    queue.push(1, 0, gray(1));
+    queue.push(2, 0, gray(2));
+    queue.push_front(0, 0, gray(0));
+
+    assert_eq!(positions(&queue), vec![(0, 0), (1, 0), (2, 0)]);
+    assert_eq!(queue.pop_front().map(|(x, y, _)| (x, y)), Some((0, 0)));
+    assert_eq!(queue.len(), 2);
+}
+
+#[test]
+fn pushing_a_queued_position_replaces_it() {
+    let mut queue = Queue::new();
+    queue.push(1, 0, gray(1));
+    queue.push(2, 0, gray(2));
+    queue.push(1, 0, gray(3));
+
+    assert_eq!(queue.len(), 2);
+    assert_eq!(positions(&queue), vec![(2, 0), (1, 0)]);
+    assert_eq!(queue.pop_front().map(|(x, y, _)| (x, y)), Some((2, 0)));
+    assert_eq!(queue.pop_front().map(|(_, _, color)| color.to_rgb()), Some((3, 3, 3)));
+    assert!(queue.pop_front().is_none());
+}
+
+#[test]
+fn cancelled_entries_are_skipped() {
+    let mut queue = Queue::new();
+    queue.extend([(0, 0, gray(0)), (1, 0, gray(1)), (2, 0, gray(2))]);
+
+    assert!(queue.cancel(0, 0));
+    assert!(!queue.cancel(0, 0));
+    assert_eq!(queue.remove(1).map(|(x, y, _)| (x, y)), Some((2, 0)));
+    assert_eq!(positions(&queue), vec![(1, 0)]);
+}
+
+#[test]
+fn moves_entries_by_their_visible_index() {
+    let mut queue = Queue::new();
+    queue.extend([(0, 0, gray(0)), (1, 0, gray(1)), (2, 0, gray(2)), (3, 0, gray(3))]);
+    queue.cancel(1, 0);
+
+    assert!(queue.move_entry(2, 0));
+    assert_eq!(positions(&queue), vec![(3, 0), (0, 0), (2, 0)]);
+    assert!(!queue.move_entry(0, 3));
+}
+
+#[test]
+fn retains_matching_entries() {
+    let mut queue = Queue::new();
+    queue.extend((0..10).map(|x| (x, 0, gray(x as u8))));
+    queue.retain(|(x, _, _)| x % 2 == 0);
+
+    assert_eq!(queue.len(), 5);
+    assert!(!queue.cancel(1, 0));
+    assert!(queue.cancel(2, 0));
+}
+
+#[test]
+fn queues_a_large_template_quickly() {
+    let mut queue = Queue::new();
+    queue.extend((0..500).flat_map(|y| (0..500).map(move |x| (x, y, gray(0)))));
+    queue.extend((0..500).flat_map(|y| (0..500).map(move |x| (x, y, gray(1)))));
+
+    assert_eq!(queue.len(), 250_000);
+    while queue.pop_front().is_some() {}
+    assert!(queue.is_empty());
+}
@@ -0,0 +1,154 @@
+use std::thread;
+use std::time::{
+    Duration,
+    Instant
+};
+
+use pixels_canvas::prelude::*;
+use pixels_server::Args;
+use pixels_util::prelude::*;
+
+const WIDTH: u32 = 16;
+const HEIGHT: u32 = 8;
+
+/// Arguments for a small server on a free port
+fn args() -> Args {
+    Args {
+        address: String::from("127.0.0.1:0"),
+        width: WIDTH,
+        height: HEIGHT,
+        ..Args::default()
+    }
+}
+
+/// Starts a server and returns its url
+fn server(args: Args) -> String {
+    let address = pixels_server::spawn(args).unwrap();
+    format!("http://{address}")
+}
+
+fn client(url: &str) -> Client {
+    let mut client = Client::new(ClientConfig::new(url.to_string()));
+    client.auth(String::from("dev")).unwrap();
+    client
+}
+
+fn canvas(url: &str) -> Canvas {
+    Canvas::new(ClientConfig::new(url.to_string()), String::from("dev")).unwrap()
+}
+
+fn red() -> Color {
+    Color::from_rgb(255, 0, 0)
+}
+
+#[test]
+fn authenticates_with_a_known_refresh_token() {
+    let url = server(args());
+    let mut client = Client::new(ClientConfig::new(url));
+
+    assert!(client.auth(String::from("dev")).is_ok());
+    assert!(matches!(client.auth(String::from("wrong")), Err(CanvasError::RefreshRevoked)));
+}
+
+#[test]
+fn reads_the_canvas_size_and_pixels() {
+    let mut client = client(&server(args()));
+
+    assert_eq!(client.canvas_size().unwrap(), (WIDTH, HEIGHT));
+
+    let pixels = client.canvas_pixels((WIDTH, HEIGHT)).unwrap();
+    assert_eq!(pixels.len(), (WIDTH * HEIGHT * 3) as usize);
+    assert!(pixels.iter().all(|channel| *channel == 255));
+}
+
+#[test]
+fn rejects_pixels_that_dont_cover_the_canvas() {
+    let mut client = client(&server(args()));
+
+    assert!(matches!(client.canvas_pixels((WIDTH, HEIGHT + 1)), Err(CanvasError::Malformed(_))));
+}
+
+#[test]
+fn sets_a_pixel() {
+    let mut client = client(&server(args()));
+
+    let (remaining, reset) = client.canvas_set_pixel(1, 2, red()).unwrap();
+    assert_eq!(remaining, 0);
+    assert!(reset > 0.0);
+
+    let pixels = client.canvas_pixels((WIDTH, HEIGHT)).unwrap();
+    let index = ((2 * WIDTH + 1) * 3) as usize;
+    assert_eq!(&pixels[index..index + 3], &[255, 0, 0]);
+}
+
+#[test]
+fn reauthenticates_once_the_access_token_expired() {
+    let mut client = client(&server(Args { access_ttl: 1, ..args() }));
+
+    thread::sleep(Duration::from_millis(1100));
+    assert!(client.canvas_pixels((WIDTH, HEIGHT)).is_ok());
+}
+
+#[test]
+fn reports_rate_limits() {
+    let mut client = client(&server(Args { reset: 60.0, ..args() }));
+
+    client.canvas_set_pixel(0, 0, red()).unwrap();
+    match client.canvas_set_pixel(1, 0, red()) {
+        Err(CanvasError::RateLimited(reset)) => assert!(reset > 0.0),
+        result => panic!("expected a rate limit, got {result:?}"),
+    }
+}
+
+#[test]
+fn rate_limits_start_the_cooldown() {
+    let mut canvas = canvas(&server(Args { requests: 0, reset: 60.0, ..args() }));
+
+    assert!(matches!(canvas.set_pixel(0, 0, red()), Err(CanvasError::Cooldown(_))));
+    assert!(canvas.get_cooldown() > 0.0);
+    assert!(matches!(canvas.set_pixel(0, 0, red()), Err(CanvasError::Cooldown(_))));
+}
+
+#[test]
+fn the_last_placement_starts_the_cooldown() {
+    let mut canvas = canvas(&server(Args { reset: 60.0, ..args() }));
+
+    canvas.set_pixel(3, 3, red()).unwrap();
+    assert_eq!(canvas.get_pixel(3, 3).unwrap().to_rgb(), (255, 0, 0));
+    assert!(canvas.get_cooldown() > 0.0);
+}
+
+#[test]
+fn applies_pixels_from_the_stream() {
+    let url = server(args());
+    let mut canvas = canvas(&url);
+    assert!(canvas.is_subscribed());
+    canvas.subscribe().unwrap();
+
+    client(&url).canvas_set_pixel(3, 4, red()).unwrap();
+
+    let start = Instant::now();
+    while canvas.get_pixel(3, 4).unwrap().to_rgb() != (255, 0, 0) {
+        assert!(start.elapsed() < Duration::from_secs(5), "no update arrived");
+        assert!(canvas.update_from_stream());
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn places_an_element_through_the_queue() {
+    let mut canvas = canvas(&server(args()));
+    let mut pixels = Pixels::new((2, 1));
+    pixels.set(0, 0, red());
+    pixels.set(1, 0, red());
+
+    let mut element = Element::from_pixels(pixels);
+    element.set_position(5, 5);
+    let placer = Placer::new(element);
+
+    assert_eq!(placer.tick(&mut canvas).unwrap(), Some((5, 5)));
+    assert_eq!(canvas.queue().len(), 1);
+
+    placer.cancel(&mut canvas);
+    assert!(canvas.queue().is_empty());
+}
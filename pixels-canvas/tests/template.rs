@@ -0,0 +1,33 @@
+use std::env;
+use std::fs;
+
+use pixels_canvas::prelude::*;
+use pixels_util::prelude::*;
+
+#[test]
+fn saves_the_palette_with_the_template() {
+    let dir = env::temp_dir().join(format!("pixels-template-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let image = dir.join("art.png");
+    let mut pixels = Pixels::new((2, 1));
+    pixels.set(0, 0, Color::from_rgb(250, 10, 10));
+    pixels.set(1, 0, Color::from_rgb(10, 10, 250));
+    pixels.save(&image).unwrap();
+
+    let mut template = Template::new(&image).unwrap();
+    template.set_palette(Some("rplace-2017"), Dither::Ordered).unwrap();
+    let quantized: Vec<_> = template.element().iter().map(|(_, color)| color.to_rgb()).collect();
+
+    let mut templates = Templates::new();
+    templates.add(template);
+    templates.save(dir.join("templates.json")).unwrap();
+
+    let loaded = Templates::load(dir.join("templates.json")).unwrap();
+    let template = loaded.get(0).unwrap();
+    assert_eq!(template.get_palette(), Some("rplace-2017"));
+    assert_eq!(template.get_dither(), Dither::Ordered);
+    assert_eq!(template.element().iter().map(|(_, color)| color.to_rgb()).collect::<Vec<_>>(), quantized);
+
+    fs::remove_dir_all(dir).unwrap();
+}
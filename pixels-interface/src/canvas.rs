@@ -6,10 +6,43 @@ use bevy_ecs::prelude::*;
 use macroquad::prelude::*;
 use pixels_canvas::prelude::*;
 
-use pixels_util::color::Color;
+use pixels_util::color::{
+    BlendMode,
+    Color
+};
+use pixels_util::pixels::{
+    Pixels,
+    MAX_DIRTY
+};
+use pixels_util::region::Region;
 use crate::state::ToolType;
 
 use super::State;
+use crate::state::{
+    save_cooldown,
+    save_queue
+};
+
+const DIFF_COLOR: Color = Color {
+    r: 1.0,
+    g: 0.0,
+    b: 1.0,
+    a: 1.0,
+};
+
+const GRID_COLOR: Color = Color {
+    r: 0.0,
+    g: 0.0,
+    b: 0.0,
+    a: 0.25,
+};
+
+const CROSSHAIR_COLOR: Color = Color {
+    r: 1.0,
+    g: 1.0,
+    b: 1.0,
+    a: 0.8,
+};
 
 #[derive(Resource)]
 pub struct CanvasContainer {
@@ -22,6 +55,99 @@ impl CanvasContainer {
     }
 }
 
+/// The gpu only blends normally, so the layers up to the last one blended otherwise are composited
+/// into `base` on the cpu and every layer above it keeps a texture of its own
+#[derive(Resource)]
+pub struct CanvasTexture {
+    pub base: Texture2D,
+    pub layers: Vec<Texture2D>,
+    split: usize,
+    names: Vec<String>,
+}
+
+impl CanvasTexture {
+    pub fn new(canvas: &mut Canvas) -> Self {
+        canvas.take_dirty();
+
+        let split = split_index(canvas);
+        let base = canvas.merge_layers_region(split + 1, Region::from_size(canvas.size()));
+
+        Self {
+            base: new_texture(&base),
+            layers: canvas.layers()[split + 1..]
+                .iter()
+                .map(|layer| new_texture(layer.pixels()))
+                .collect(),
+            split,
+            names: canvas.layers().iter().map(|layer| layer.name().to_string()).collect(),
+        }
+    }
+
+    /// Whether layers were added, removed, moved or changed which of them the gpu can blend
+    fn is_outdated(&self, canvas: &Canvas) -> bool {
+        self.split != split_index(canvas)
+            || !self.names.iter().map(String::as_str).eq(canvas.layers().iter().map(Layer::name))
+    }
+
+    fn delete(&self) {
+        self.base.delete();
+        for texture in self.layers.iter() {
+            texture.delete();
+        }
+    }
+}
+
+/// Index of the last visible layer the gpu can't blend, the bottom one when all blend normally
+fn split_index(canvas: &Canvas) -> usize {
+    canvas
+        .layers()
+        .iter()
+        .rposition(|layer| layer.is_visible() && layer.get_blend() != BlendMode::Normal)
+        .unwrap_or(0)
+}
+
+fn new_texture(pixels: &Pixels) -> Texture2D {
+    let texture = Texture2D::from_rgba8(
+        pixels.width() as u16,
+        pixels.height() as u16,
+        pixels.as_bytes(),
+    );
+    texture.set_filter(FilterMode::Nearest);
+    texture
+}
+
+fn update_texture(texture: Texture2D, pixels: Pixels, region: Region) {
+    texture.update_part(
+        &Image {
+            bytes: pixels.into_bytes(),
+            width: region.width as u16,
+            height: region.height as u16,
+        },
+        region.x as i32,
+        region.y as i32,
+        region.width as i32,
+        region.height as i32,
+    );
+}
+
+/// What the diff and the image layer were last built from, they're only rebuilt once it changes
+#[derive(Resource, Default)]
+pub struct ImageCache {
+    diff: Option<(u64, u64)>,
+    image: Option<ImageKey>,
+}
+
+#[derive(PartialEq)]
+struct ImageKey {
+    templates: u64,
+    queue: u64,
+    diff: Option<(u64, u64)>,
+    show_diff: bool,
+    shape: Vec<(u32, u32)>,
+    color: [f32; 3],
+    preview: Option<(usize, u32, u32)>,
+}
+
 #[derive(Resource)]
 pub struct CanvasTimer {
     pub instance: Timer,
@@ -34,21 +160,33 @@ impl CanvasTimer {
 }
 
 pub fn register_systems(
-    canvas: Canvas,
+    mut canvas: Canvas,
     world: &mut World,
     update_schedule: &mut Schedule,
     draw_schedule: &mut Schedule,
 ) {
     draw_schedule.add_systems((
+        draw_image,
         draw,
-        draw_image
-    ));
+        draw_grid,
+        draw_crosshair
+    ).chain());
 
     update_schedule.add_systems((
         update_cooldown,
+        update_stream,
         update.run_if(not(is_cooldown)),
+        update_placer.run_if(not(is_cooldown)).run_if(not(is_revoked)).before(update_queue),
+        update_diff.after(update_stream).after(update),
+        update_queue.run_if(not(is_cooldown)).run_if(not(is_revoked)),
     ));
 
+    if let Err(e) = canvas.subscribe() {
+        println!("live updates are unavailable, polling instead: {e}");
+    }
+
+    world.insert_resource(CanvasTexture::new(&mut canvas));
+    world.insert_resource(ImageCache::default());
     world.insert_resource(CanvasContainer::new(canvas));
     world.insert_resource(CanvasTimer::new(Timer::new(
         Duration::from_secs(5),
@@ -60,8 +198,17 @@ fn is_cooldown(state: Res<State>) -> bool {
     state.cooldown != 0.0
 }
 
+fn is_revoked(state: Res<State>) -> bool {
+    state.revoked
+}
+
+/// Tracks the cooldown and saves it whenever a placement starts a new one
 pub fn update_cooldown(mut state: ResMut<State>, container: ResMut<CanvasContainer>) {
-    state.cooldown = container.canvas.get_cooldown();
+    let cooldown = container.canvas.get_cooldown();
+    if cooldown > state.cooldown {
+        save_cooldown(cooldown);
+    }
+    state.cooldown = cooldown;
 }
 
 pub fn update(
@@ -69,40 +216,231 @@ pub fn update(
     mut timer: ResMut<CanvasTimer>,
     mut container: ResMut<CanvasContainer>,
 ) {
-    if timer.instance.tick(time.delta()).finished() {
-        container
-            .canvas
-            .update_main_layer()
-            .expect("couldn't update canvas pixels");
+    if !timer.instance.tick(time.delta()).finished() || container.canvas.is_subscribed() {
+        return;
+    }
+
+    if container.canvas.subscribe().is_ok() {
+        println!("live updates are available again");
+    } else if let Err(e) = container.canvas.update_main_layer() {
+        println!("couldn't update canvas pixels: {e}");
+    }
+}
+
+pub fn update_stream(mut container: ResMut<CanvasContainer>) {
+    if container.canvas.is_subscribed() && !container.canvas.update_from_stream() {
+        println!("live updates are disconnected, polling instead");
+    }
+}
+
+pub fn update_queue(mut state: ResMut<State>, mut container: ResMut<CanvasContainer>) {
+    let canvas = &mut container.canvas;
+    let queued = canvas.queue().len();
+
+    match canvas.place_queued() {
+        Ok(Some((x, y))) => {
+            println!("placed pixel at {x}, {y}, {} queued", canvas.queue().len());
+        }
+        Ok(None) => {}
+        Err(CanvasError::RefreshRevoked) => {
+            println!("refresh token is revoked, placing stopped, log in again and restart");
+            state.set_placer(None);
+            state.revoked = true;
+        }
+        Err(e) => {
+            println!("couldn't place queued pixel: {e}");
+        }
+    }
+
+    if canvas.queue().len() != queued {
+        save_queue(canvas.queue());
+    }
+}
+
+pub fn update_placer(state: Res<State>, mut container: ResMut<CanvasContainer>) {
+    let Some(placer) = state.placer.as_ref() else {
+        return;
+    };
+
+    if container.canvas.queue().is_empty() {
+        placer.refresh(&mut container.canvas);
     }
 }
 
-pub fn draw(state: Res<State>, container: Res<CanvasContainer>) {
-    for ((x, y), color) in container.canvas.get_layers_merged().iter() {
-        draw_rectangle(
-            x as f32, y as f32, 1.0, 1.0,
+pub fn draw(
+    state: Res<State>,
+    mut texture: ResMut<CanvasTexture>,
+    mut container: ResMut<CanvasContainer>,
+) {
+    let canvas = &mut container.canvas;
+    let size = vec2(canvas.width() as f32, canvas.height() as f32);
+
+    if texture.is_outdated(canvas) {
+        texture.delete();
+        *texture = CanvasTexture::new(canvas);
+    }
+
+    let split = texture.split;
+    let mut dirty = vec![];
+    for layer in canvas.layers_mut()[..=split].iter_mut() {
+        for region in layer.take_dirty() {
+            region.merge_into(&mut dirty, MAX_DIRTY);
+        }
+    }
+    for region in dirty {
+        update_texture(texture.base, canvas.merge_layers_region(split + 1, region), region);
+    }
+
+    for (layer, texture) in canvas.layers_mut()[split + 1..].iter_mut().zip(texture.layers.iter()) {
+        for region in layer.take_dirty() {
+            update_texture(*texture, layer.pixels().crop(region), region);
+        }
+    }
+
+    let draw = |texture: Texture2D, opacity: f32| {
+        let tint = Color::new(1.0, 1.0, 1.0, opacity);
+        draw_texture_ex(
+            texture, 0.0, 0.0,
             convert_color(if state.cooldown == 0.0 {
-                color
+                tint
             } else {
-                dim_color(color)
-            })
+                dim_color(tint)
+            }),
+            DrawTextureParams {
+                dest_size: Some(size),
+                ..Default::default()
+            },
         );
+    };
+
+    draw(texture.base, 1.0);
+    for (layer, texture) in canvas.layers()[split + 1..].iter().zip(texture.layers.iter()) {
+        if layer.is_visible() {
+            draw(*texture, layer.get_opacity());
+        }
     }
 }
 
-pub fn draw_image(mut state: ResMut<State>, mut container: ResMut<CanvasContainer>) {
-    let pos = super::mouse_world_pos(state.camera_state.instance);
+/// Outlines every visible pixel once the camera is zoomed in far enough
+pub fn draw_grid(state: Res<State>, container: Res<CanvasContainer>) {
+    let zoom = state.camera_state.zoom;
+    if !state.show_grid || zoom < state.config.camera.grid_zoom {
+        return;
+    }
+
+    let size = vec2(container.canvas.width() as f32, container.canvas.height() as f32);
+    let camera = state.camera_state.instance;
+    let min = camera.screen_to_world(vec2(0.0, 0.0)).max(Vec2::ZERO).floor();
+    let max = camera.screen_to_world(vec2(screen_width(), screen_height())).min(size).ceil();
+    if min.x > max.x || min.y > max.y {
+        return;
+    }
+
+    let color = convert_color(GRID_COLOR);
+    for x in min.x as u32..=max.x as u32 {
+        draw_line(x as f32, min.y, x as f32, max.y, 1.0 / zoom, color);
+    }
+    for y in min.y as u32..=max.y as u32 {
+        draw_line(min.x, y as f32, max.x, y as f32, 1.0 / zoom, color);
+    }
+}
 
-    container.canvas.get_image_layer_mut().clean();
+/// Marks the hovered pixel and previews the brush color on it
+pub fn draw_crosshair(state: Res<State>, container: Res<CanvasContainer>) {
+    let Some((x, y)) = super::hovered_pixel(state.camera_state.instance, &container.canvas) else {
+        return;
+    };
+
+    let (x, y) = (x as f32, y as f32);
+    let (width, height) = (container.canvas.width() as f32, container.canvas.height() as f32);
+    let thickness = 2.0 / state.camera_state.zoom;
+    let color = convert_color(CROSSHAIR_COLOR);
+    let faded = convert_color(Color { a: 0.3, ..CROSSHAIR_COLOR });
+
+    draw_line(0.0, y + 0.5, x, y + 0.5, thickness / 2.0, faded);
+    draw_line(x + 1.0, y + 0.5, width, y + 0.5, thickness / 2.0, faded);
+    draw_line(x + 0.5, 0.0, x + 0.5, y, thickness / 2.0, faded);
+    draw_line(x + 0.5, y + 1.0, x + 0.5, height, thickness / 2.0, faded);
+
+    let paints = matches!(
+        state.selected_tool,
+        ToolType::Brush | ToolType::Line | ToolType::Rectangle | ToolType::Fill
+    );
+    if paints {
+        let [r, g, b] = state.color;
+        draw_rectangle(x, y, 1.0, 1.0, macroquad::color::Color::new(r, g, b, 0.75));
+    }
+    draw_rectangle_lines(x, y, 1.0, 1.0, thickness, color);
+}
 
-    if state.selected_tool != ToolType::Placer {
+/// Compares the placer with the canvas once either of them changed
+pub fn update_diff(
+    mut state: ResMut<State>,
+    container: Res<CanvasContainer>,
+    mut cache: ResMut<ImageCache>,
+) {
+    let revision = container.canvas.get_main_layer().revision();
+    let key = state.placer.as_ref().map(|_| (state.placer_revision, revision));
+    if key == cache.diff {
         return;
     }
 
-    if state.image.is_some() {
-        state.image.as_mut().unwrap().set_position(pos.x as u32, pos.y as u32);
-        container.canvas.get_image_layer_mut().draw(state.image.clone().unwrap());
+    cache.diff = key;
+    state.diff = state.placer.as_ref().map(|placer| {
+        container.canvas.diff(placer.element())
+    });
+}
+
+/// Redraws the overlays into a fresh layer once what they show changed,
+/// only the pixels that differ from the last build reach the texture
+pub fn draw_image(
+    state: Res<State>,
+    mut container: ResMut<CanvasContainer>,
+    mut cache: ResMut<ImageCache>,
+) {
+    let pos = super::mouse_world_pos(state.camera_state.instance);
+    let preview = state.selected
+        .zip(state.selected_template())
+        .filter(|(_, template)| state.selected_tool == ToolType::Placer && !template.is_locked());
+
+    let key = ImageKey {
+        templates: state.templates.revision(),
+        queue: container.canvas.queue().revision(),
+        diff: cache.diff,
+        show_diff: state.show_diff,
+        shape: state.shape.clone(),
+        color: state.color,
+        preview: preview.map(|(id, _)| (id, pos.x as u32, pos.y as u32)),
+    };
+    if cache.image.as_ref() == Some(&key) {
+        return;
+    }
+    cache.image = Some(key);
+
+    let mut layer = Layer::new(IMAGE_LAYER, container.canvas.size(), 1.0);
+    state.templates.draw(&mut layer);
+
+    if let Some(diff) = state.diff.as_ref().filter(|_| state.show_diff) {
+        for (x, y, _) in diff.wrong.iter() {
+            layer.set_pixel(*x, *y, DIFF_COLOR);
+        }
     }
+
+    for (x, y, color) in container.canvas.queue().iter() {
+        layer.set_pixel(*x, *y, *color);
+    }
+    let color = Color::from(state.color);
+    for (x, y) in state.shape.iter() {
+        layer.set_pixel(*x, *y, color);
+    }
+
+    if let Some((_, template)) = preview {
+        let mut preview = template.element().clone();
+        preview.set_position(pos.x as u32, pos.y as u32);
+        layer.draw(preview);
+    }
+
+    container.canvas.get_image_layer_mut().set_pixels(layer.into_pixels());
 }
 
 pub fn dim_color(color: Color) -> Color {
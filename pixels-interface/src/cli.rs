@@ -0,0 +1,250 @@
+use std::error::Error;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{
+    Duration,
+    Instant
+};
+
+use clap::Subcommand;
+
+use pixels_canvas::prelude::*;
+use pixels_util::prelude::*;
+
+use super::{
+    Args,
+    state,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(clap::Args)]
+pub struct ExportArgs {
+    /// Png file to write
+    path: PathBuf,
+
+    /// Include the overlay layers and saved templates instead of the main layer only
+    #[arg(long)]
+    merged: bool,
+
+    /// Crop to x,y,width,height
+    #[arg(long)]
+    region: Option<Region>,
+
+    /// Integer upscale factor
+    #[arg(long, default_value_t = 1)]
+    scale: u32,
+}
+
+#[derive(clap::Args)]
+pub struct RecordArgs {
+    /// Recording file to write
+    path: PathBuf,
+
+    /// Seconds between frames
+    #[arg(long, default_value_t = 10.0)]
+    interval: f32,
+
+    /// Record only x,y,width,height
+    #[arg(long)]
+    region: Option<Region>,
+
+    /// Stop after this many seconds, runs until interrupted otherwise
+    #[arg(long)]
+    duration: Option<f32>,
+}
+
+#[derive(clap::Args)]
+pub struct RenderArgs {
+    /// Recording file to read
+    recording: PathBuf,
+
+    /// A .gif file, or a directory to fill with numbered png frames
+    output: PathBuf,
+
+    /// Frames per second of the gif
+    #[arg(long, default_value_t = 10.0)]
+    fps: f32,
+
+    /// Integer upscale factor
+    #[arg(long, default_value_t = 1)]
+    scale: u32,
+}
+
+#[derive(clap::Args)]
+pub struct PlaceArgs {
+    x: u32,
+    y: u32,
+
+    /// Hex color as rrggbb
+    #[arg(value_parser = parse_color)]
+    color: Color,
+}
+
+#[derive(Subcommand)]
+pub enum TemplateCommand {
+    /// Places the image pixel by pixel until the canvas matches it
+    Run {
+        /// Image to place
+        image: PathBuf,
+
+        /// Top left corner as x,y
+        #[arg(long, value_parser = parse_point, default_value = "0,0")]
+        at: (u32, u32),
+    },
+}
+
+pub fn export(args: &Args, export: &ExportArgs) -> Result<(), Box<dyn Error>> {
+    let mut canvas = super::connect(args)?;
+
+    if export.merged {
+        let templates = state::load_templates();
+        templates.draw(canvas.get_image_layer_mut());
+    }
+
+    canvas
+        .export(&ExportConfig {
+            merged: export.merged,
+            region: export.region,
+            scale: export.scale,
+        })
+        .save(&export.path)?;
+
+    println!("saved canvas to {}", export.path.display());
+    Ok(())
+}
+
+pub fn record(args: &Args, record: &RecordArgs) -> Result<(), Box<dyn Error>> {
+    let mut canvas = super::connect(args)?;
+    if let Err(e) = canvas.subscribe() {
+        println!("live updates are unavailable, polling instead: {e}");
+    }
+
+    let interval = Duration::from_secs_f32(record.interval);
+    canvas.start_recording(&record.path, record.region, interval)?;
+    println!("recording to {}", record.path.display());
+
+    let start = Instant::now();
+    let mut polled = Instant::now();
+
+    while record.duration.is_none_or(|duration| start.elapsed().as_secs_f32() < duration) {
+        if canvas.is_subscribed() {
+            canvas.update_from_stream();
+        } else if polled.elapsed() >= interval {
+            polled = Instant::now();
+            if let Err(e) = canvas.update_main_layer() {
+                println!("couldn't update canvas pixels: {e}");
+            }
+        }
+
+        if !canvas.is_recording() {
+            return Err("couldn't write the recording".into())
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    let frames = canvas.stop_recording().map_or(0, |recorder| recorder.frames());
+    println!("recorded {frames} frames");
+    Ok(())
+}
+
+pub fn render(render: &RenderArgs) -> Result<(), Box<dyn Error>> {
+    let mut recording = Recording::open(&render.recording)?;
+
+    let extension = render.output.extension().and_then(|ext| ext.to_str());
+    let frames = match extension {
+        Some("gif") => recording.render_gif(
+            &render.output,
+            Duration::from_secs_f32(1.0 / render.fps.max(0.1)),
+            render.scale,
+        )?,
+        Some("png" | "apng") => return Err("apng isn't supported, render to a .gif or a directory".into()),
+        _ => recording.render_frames(&render.output, render.scale)?,
+    };
+
+    println!("rendered {frames} frames to {}", render.output.display());
+    Ok(())
+}
+
+pub fn place(args: &Args, place: &PlaceArgs) -> Result<(), Box<dyn Error>> {
+    let mut canvas = super::connect(args)?;
+    let result = canvas.set_pixel(place.x, place.y, place.color);
+    state::save_cooldown(canvas.get_cooldown());
+    result?;
+
+    println!("placed {} at {}, {}", place.color.to_hex(ColorMode::RGB), place.x, place.y);
+    Ok(())
+}
+
+pub fn template(args: &Args, command: &TemplateCommand) -> Result<(), Box<dyn Error>> {
+    let TemplateCommand::Run { image, at } = command;
+
+    let mut element = Element::new(image)?;
+    if let Some(palette) = super::load_palette(args)? {
+        element.quantize(&palette, args.dither);
+    }
+    element.set_position(at.0, at.1);
+
+    let mut canvas = super::connect(args)?;
+    if let Err(e) = canvas.subscribe() {
+        println!("live updates are unavailable, polling instead: {e}");
+    }
+
+    let placer = Placer::new(element);
+    let mut polled = Instant::now();
+
+    loop {
+        if canvas.is_subscribed() {
+            canvas.update_from_stream();
+        } else if polled.elapsed() >= POLL_INTERVAL {
+            polled = Instant::now();
+            if let Err(e) = canvas.update_main_layer() {
+                println!("couldn't update canvas pixels: {e}");
+            }
+        }
+
+        match placer.tick(&mut canvas) {
+            Ok(Some((x, y))) => {
+                println!("placed pixel at {x}, {y}, {} left", canvas.queue().len());
+                state::save_cooldown(canvas.get_cooldown());
+            }
+            Ok(None) if canvas.get_cooldown() == 0.0 && canvas.queue().is_empty() => break,
+            Ok(None) => {}
+            Err(CanvasError::RefreshRevoked) => return Err(CanvasError::RefreshRevoked.into()),
+            Err(e) => println!("couldn't place template pixel: {e}"),
+        }
+
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    println!("template is complete");
+    Ok(())
+}
+
+/// Checks the token and prints the canvas size without downloading the canvas,
+/// the cooldown is read from the local cache file and is not asked from the server
+pub fn status(args: &Args) -> Result<(), Box<dyn Error>> {
+    let (config, refresh) = super::credentials(args)?;
+    let mut client = Client::new(config);
+    client.auth(refresh)?;
+    let (width, height) = client.canvas_size()?;
+
+    println!("size: {width}x{height}");
+    match state::load_cooldown() {
+        cooldown if cooldown > 0.0 => println!("cooldown (local cache): {cooldown:.1} secs"),
+        _ => println!("cooldown (local cache): ready"),
+    }
+    Ok(())
+}
+
+fn parse_color(value: &str) -> Result<Color, String> {
+    Color::from_hex(value, ColorMode::RGB).ok_or_else(|| format!("invalid color {value}, expected rrggbb"))
+}
+
+fn parse_point(value: &str) -> Result<(u32, u32), String> {
+    let (x, y) = value.split_once(',').ok_or_else(|| format!("invalid point {value}, expected x,y"))?;
+    Ok((
+        x.trim().parse().map_err(|e| format!("invalid x {x}: {e}"))?,
+        y.trim().parse().map_err(|e| format!("invalid y {y}: {e}"))?,
+    ))
+}
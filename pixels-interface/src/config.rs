@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{
+    self,
+    OpenOptions
+};
+use std::io::{
+    self,
+    Write
+};
+use std::path::{
+    Path,
+    PathBuf
+};
+
+use serde::{
+    Deserialize,
+    Serialize
+};
+
+use pixels_util::prelude::*;
+
+/// Settings read from `config.toml` in the config dir, command line arguments override them
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Config {
+    /// Base url of the pixels server
+    pub server: Option<String>,
+    /// Refresh token written inline, prefer `token_file` or `token_env`
+    pub token: Option<String>,
+    /// File holding the refresh token
+    pub token_file: Option<PathBuf>,
+    /// Environment variable holding the refresh token
+    pub token_env: Option<String>,
+    /// Brush color as rrggbb
+    pub color: Option<String>,
+    pub window: WindowConfig,
+    pub camera: CameraConfig,
+    /// Action name to key chords, like `undo = "ctrl+z, u"`
+    pub keys: BTreeMap<String, String>,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WindowConfig {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fullscreen: bool,
+}
+
+/// Zooms are in screen pixels per canvas pixel
+#[derive(Deserialize, Serialize)]
+#[serde(default)]
+pub struct CameraConfig {
+    pub min_zoom: f32,
+    pub max_zoom: f32,
+    /// Keyboard panning in screen pixels per second
+    pub pan_speed: f32,
+    /// Zoom from which the pixel grid shows
+    pub grid_zoom: f32,
+    /// How quickly the camera catches up with where it's headed, 0 moves instantly
+    pub smoothing: f32,
+}
+
+impl Config {
+    /// Reads the config file, a missing file gives the defaults
+    pub fn load() -> Result<Self, String> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default())
+        };
+
+        match fs::read_to_string(&path) {
+            Ok(text) => toml::from_str(&text)
+                .map_err(|e| format!("couldn't parse {}: {e}", path.display())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("couldn't read {}: {e}", path.display())),
+        }
+    }
+
+    pub fn path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join("config.toml"))
+    }
+
+    /// The file `login` stores the refresh token in
+    pub fn token_path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join("token"))
+    }
+
+    /// Resolves the refresh token from the inline value, the environment variable,
+    /// the token file and finally the file written by `login`
+    pub fn token(&self) -> Option<String> {
+        self.token
+            .clone()
+            .or_else(|| self.token_env.as_ref().and_then(|name| env::var(name).ok()))
+            .or_else(|| self.token_file.as_ref().and_then(|path| read_token(path)))
+            .or_else(|| Self::token_path().and_then(|path| read_token(&path)))
+    }
+
+    pub fn color(&self) -> Option<Color> {
+        Color::from_hex(self.color.as_ref()?, ColorMode::RGB)
+    }
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        Self {
+            min_zoom: 0.25,
+            max_zoom: 64.0,
+            pan_speed: 600.0,
+            grid_zoom: 8.0,
+            smoothing: 12.0,
+        }
+    }
+}
+
+/// Writes the refresh token readable by the owner only
+pub fn save_token(token: &str) -> io::Result<PathBuf> {
+    let path = Config::token_path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let mut file = options.open(&path)?;
+    #[cfg(unix)]
+    file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+
+    writeln!(file, "{token}")?;
+    Ok(path)
+}
+
+fn read_token(path: &Path) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty())
+}
+
+fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("pixels-client"))
+}
@@ -3,22 +3,29 @@ use egui_macroquad::egui::Pos2;
 use bevy_ecs::prelude::*;
 use macroquad::prelude::*;
 use pixels_canvas::prelude::*;
+use pixels_canvas::shape;
 
 use super::{
     CanvasContainer,
     ToolType,
     State,
 };
+use crate::keymap::Action;
+use crate::state::save_queue;
 use pixels_util::color::Color;
 
+const ZOOM_STEP: f32 = 1.25;
+const MAX_FILL: usize = 4096;
+
 pub fn register_systems(update_schedule: &mut Schedule) {
     update_schedule.add_systems((
         update_zoom.run_if(not(is_panel_focused)),
         update_mouse.run_if(not(is_panel_focused)),
-        update_tool_move.run_if(not(is_panel_focused)),
+        update_actions.run_if(not(is_typing)),
         update_tool_draw.run_if(not(is_panel_focused)),
         update_tool_pick.run_if(not(is_panel_focused)),
         update_tool_place.run_if(not(is_panel_focused)),
+        update_tool_shape.run_if(not(is_panel_focused)),
     ));
 }
 
@@ -27,61 +34,173 @@ fn is_panel_focused(state: Res<State>) -> bool {
     state.menu_state.area.contains(pos) || state.focus
 }
 
+fn is_typing(state: Res<State>) -> bool {
+    state.typing
+}
+
+pub fn update_actions(mut state: ResMut<State>, mut container: ResMut<CanvasContainer>) {
+    let tools = [
+        (Action::ToolMove, ToolType::Mover),
+        (Action::ToolBrush, ToolType::Brush),
+        (Action::ToolPicker, ToolType::Picker),
+        (Action::ToolPlacer, ToolType::Placer),
+        (Action::ToolLine, ToolType::Line),
+        (Action::ToolRectangle, ToolType::Rectangle),
+        (Action::ToolFill, ToolType::Fill),
+    ];
+    for (action, tool) in tools {
+        if state.keymap.is_pressed(action) {
+            state.selected_tool = tool;
+        }
+    }
+
+    if state.keymap.is_pressed(Action::ZoomIn) {
+        zoom_at_mouse(&mut state, ZOOM_STEP);
+    }
+    if state.keymap.is_pressed(Action::ZoomOut) {
+        zoom_at_mouse(&mut state, 1.0 / ZOOM_STEP);
+    }
+
+    let pan = [
+        (Action::PanLeft, vec2(-1.0, 0.0)),
+        (Action::PanRight, vec2(1.0, 0.0)),
+        (Action::PanUp, vec2(0.0, -1.0)),
+        (Action::PanDown, vec2(0.0, 1.0)),
+    ];
+    let direction: Vec2 = pan
+        .iter()
+        .filter(|(action, _)| state.keymap.is_down(*action))
+        .map(|(_, direction)| direction)
+        .sum();
+    let speed = state.config.camera.pan_speed * get_frame_time() / state.camera_state.target_zoom;
+    state.camera_state.target_position += direction * speed;
+
+    if state.keymap.is_pressed(Action::FitWindow) {
+        let size = vec2(container.canvas.width() as f32, container.canvas.height() as f32);
+        let state = &mut *state;
+        state.camera_state.fit(size, &state.config.camera);
+    }
+    if state.keymap.is_pressed(Action::GoTo) {
+        state.show_goto = !state.show_goto;
+    }
+
+    if state.keymap.is_pressed(Action::ToggleOverlays) {
+        let layer = container.canvas.get_image_layer_mut();
+        layer.set_visible(!layer.is_visible());
+    }
+    if state.keymap.is_pressed(Action::ToggleDiff) {
+        state.show_diff = !state.show_diff;
+    }
+    if state.keymap.is_pressed(Action::ToggleGrid) {
+        state.show_grid = !state.show_grid;
+    }
+    if state.keymap.is_pressed(Action::ToggleLayers) {
+        state.show_layers = !state.show_layers;
+    }
+    if state.keymap.is_pressed(Action::ToggleTemplates) {
+        state.show_templates = !state.show_templates;
+    }
+    if state.keymap.is_pressed(Action::Help) {
+        state.show_help = !state.show_help;
+    }
+
+    if state.keymap.is_pressed(Action::Undo) {
+        undo(&mut state, &mut container.canvas);
+    }
+
+    if state.keymap.is_pressed(Action::ToggleQueue) {
+        state.show_queue = !state.show_queue;
+    }
+
+    if state.keymap.is_pressed(Action::ConfirmShape) {
+        confirm_shape(&mut state, &mut container.canvas);
+    }
+    if state.keymap.is_pressed(Action::CancelShape) {
+        state.shape_origin = None;
+        state.shape.clear();
+    }
+}
+
+/// Queues the previewed shape, its pixels are placed as the cooldown allows
+pub fn confirm_shape(state: &mut State, canvas: &mut Canvas) {
+    if state.shape.is_empty() {
+        return;
+    }
+
+    let color = Color::from(state.color);
+    println!("queued {} pixels", state.shape.len());
+    for (x, y) in state.shape.drain(..) {
+        canvas.queue_mut().push(x, y, color);
+    }
+
+    state.shape_origin = None;
+    save_queue(canvas.queue());
+}
+
+/// Cancels the last brush pixel while it's still queued, otherwise queues the color it
+/// replaced ahead of everything else, which costs a placement too
+fn undo(state: &mut State, canvas: &mut Canvas) {
+    let Some((x, y, color)) = state.history.pop() else {
+        return;
+    };
+
+    if canvas.queue_mut().cancel(x, y) {
+        println!("cancelled queued pixel at {x}, {y}");
+    } else {
+        canvas.queue_mut().push_front(x, y, color);
+        println!("queued revert of pixel at {x}, {y}");
+    }
+    save_queue(canvas.queue());
+}
+
+/// Backends report wildly different wheel deltas so every scrolled frame is one step
 pub fn update_zoom(mut state: ResMut<State>) {
-    state.camera_state.zoom = (state.camera_state.zoom + mouse_wheel().1 / 120.0).clamp(1.0, 10.0);
+    let wheel = mouse_wheel().1;
+    if wheel != 0.0 {
+        zoom_at_mouse(&mut state, ZOOM_STEP.powf(wheel.signum()));
+    }
+}
+
+fn zoom_at_mouse(state: &mut State, factor: f32) {
+    let zoom = state.camera_state.target_zoom * factor;
+    let mouse = vec2(mouse_position().0, mouse_position().1);
+    state.camera_state.zoom_at(zoom, mouse, &state.config.camera);
 }
 
+/// Drags the canvas with the mover tool or the middle button in any tool
 pub fn update_mouse(mut state: ResMut<State>) {
     let pos = super::mouse_world_pos(state.camera_state.instance);
 
-    if is_mouse_button_pressed(MouseButton::Left) {
+    if is_mouse_button_pressed(MouseButton::Left) || is_mouse_button_pressed(MouseButton::Middle) {
         state.camera_state.move_origin = pos;
-    } else if is_mouse_button_down(MouseButton::Left) && state.selected_tool == ToolType::Mover {
+    } else if is_mouse_button_down(MouseButton::Middle)
+        || (is_mouse_button_down(MouseButton::Left) && state.selected_tool == ToolType::Mover)
+    {
         let origin = state.camera_state.move_origin;
-        state.camera_state.position += origin - pos;
-    }
-}
-
-pub fn update_tool_move(mut state: ResMut<State>) {
-    if is_key_down(KeyCode::M) {
-        state.selected_tool = ToolType::Mover;
+        state.camera_state.pan(origin - pos);
     }
 }
 
 pub fn update_tool_draw(mut state: ResMut<State>, mut container: ResMut<CanvasContainer>) {
-    if is_key_down(KeyCode::B) {
-        state.selected_tool = ToolType::Brush;
-    }
-
     if !is_mouse_button_pressed(MouseButton::Left) {
         return;
     }
 
     if let ToolType::Brush = state.selected_tool {
-        let pos = super::mouse_world_pos(state.camera_state.instance);
+        let Some((x, y)) = super::hovered_pixel(state.camera_state.instance, &container.canvas) else {
+            return;
+        };
+        let Some(previous) = container.canvas.get_pixel(x, y) else {
+            return;
+        };
 
-        if let Err(e) = container.canvas.set_pixel(
-            pos.x as u32,
-            pos.y as u32,
-            Color::from(state.color),
-        ) {
-            match e {
-                CanvasError::Client(_e) => {
-                    panic!("couldn't set pixel");
-                }
-                CanvasError::Cooldown(cooldown) => {
-                    println!("please wait cooldown to end: {cooldown}");
-                }
-            }
-        }
+        container.canvas.queue_mut().push(x, y, Color::from(state.color));
+        state.history.push((x, y, previous));
+        save_queue(container.canvas.queue());
     }
 }
 
 pub fn update_tool_pick(mut state: ResMut<State>, container: ResMut<CanvasContainer>) {
-    if is_key_down(KeyCode::I) {
-        state.selected_tool = ToolType::Picker;
-    }
-
     if !is_mouse_button_pressed(MouseButton::Left) {
         return;
     }
@@ -98,16 +217,72 @@ pub fn update_tool_pick(mut state: ResMut<State>, container: ResMut<CanvasContai
     }
 }
 
-pub fn update_tool_place(mut state: ResMut<State>, mut _container: ResMut<CanvasContainer>) {
-    if is_key_down(KeyCode::P) {
-        state.selected_tool = ToolType::Placer;
-    }
-
+pub fn update_tool_place(mut state: ResMut<State>, mut container: ResMut<CanvasContainer>) {
     if !is_mouse_button_pressed(MouseButton::Left) {
         return;
     }
 
     if let ToolType::Placer = state.selected_tool {
-        // todo: place logic
+        let pos = super::mouse_world_pos(state.camera_state.instance);
+        let Some(template) = state.selected_template_mut() else {
+            return;
+        };
+
+        let moved = template.set_position(pos.x as u32, pos.y as u32);
+        let placer = state.selected_template()
+            .map(|template| Placer::new(template.element().clone()));
+        if let Some(placer) = state.set_placer(placer) {
+            placer.cancel(&mut container.canvas);
+        }
+
+        if moved {
+            state.save_templates();
+        }
+    }
+}
+
+/// Builds the shape preview, lines and rectangles are dragged and fills start at a click
+pub fn update_tool_shape(mut state: ResMut<State>, container: ResMut<CanvasContainer>) {
+    let canvas = &container.canvas;
+    let pos = super::mouse_world_pos(state.camera_state.instance);
+    let pixel = (
+        pos.x.clamp(0.0, canvas.width() as f32 - 1.0) as u32,
+        pos.y.clamp(0.0, canvas.height() as f32 - 1.0) as u32,
+    );
+
+    match state.selected_tool {
+        ToolType::Line | ToolType::Rectangle => {
+            if is_mouse_button_pressed(MouseButton::Left) {
+                state.shape_origin = super::hovered_pixel(state.camera_state.instance, canvas);
+            }
+
+            let Some(origin) = state.shape_origin else {
+                return;
+            };
+
+            state.shape = match state.selected_tool {
+                ToolType::Line => shape::line(origin, pixel),
+                _ => shape::rectangle(origin, pixel, state.fill_rectangle),
+            };
+
+            if !is_mouse_button_down(MouseButton::Left) {
+                state.shape_origin = None;
+            }
+        }
+        ToolType::Fill => {
+            if !is_mouse_button_pressed(MouseButton::Left) {
+                return;
+            }
+
+            let Some(start) = super::hovered_pixel(state.camera_state.instance, canvas) else {
+                return;
+            };
+
+            match shape::flood_fill(canvas.get_main_layer(), start, MAX_FILL) {
+                Some(points) => state.shape = points,
+                None => println!("the area is larger than {MAX_FILL} pixels"),
+            }
+        }
+        _ => {}
     }
 }
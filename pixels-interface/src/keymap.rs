@@ -0,0 +1,332 @@
+use std::collections::BTreeMap;
+use std::fmt::{
+    self,
+    Display,
+    Formatter
+};
+
+use macroquad::prelude::*;
+
+const MODIFIERS: [(&str, KeyCode, KeyCode); 3] = [
+    ("ctrl", KeyCode::LeftControl, KeyCode::RightControl),
+    ("shift", KeyCode::LeftShift, KeyCode::RightShift),
+    ("alt", KeyCode::LeftAlt, KeyCode::RightAlt),
+];
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Action {
+    ToolMove,
+    ToolBrush,
+    ToolPicker,
+    ToolPlacer,
+    ToolLine,
+    ToolRectangle,
+    ToolFill,
+    ConfirmShape,
+    CancelShape,
+    ZoomIn,
+    ZoomOut,
+    PanLeft,
+    PanRight,
+    PanUp,
+    PanDown,
+    FitWindow,
+    GoTo,
+    Undo,
+    ToggleOverlays,
+    ToggleDiff,
+    ToggleGrid,
+    ToggleLayers,
+    ToggleTemplates,
+    ToggleQueue,
+    Help,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Input {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+/// An input with the modifiers that have to be held, like `ctrl+z`
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Chord {
+    modifiers: Vec<&'static str>,
+    input: Input,
+}
+
+/// Maps actions to chords, every action can have several
+pub struct Keymap {
+    bindings: Vec<(Action, Chord)>,
+}
+
+impl Action {
+    pub const ALL: [Action; 25] = [
+        Action::ToolMove,
+        Action::ToolBrush,
+        Action::ToolPicker,
+        Action::ToolPlacer,
+        Action::ToolLine,
+        Action::ToolRectangle,
+        Action::ToolFill,
+        Action::ConfirmShape,
+        Action::CancelShape,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::PanLeft,
+        Action::PanRight,
+        Action::PanUp,
+        Action::PanDown,
+        Action::FitWindow,
+        Action::GoTo,
+        Action::Undo,
+        Action::ToggleOverlays,
+        Action::ToggleDiff,
+        Action::ToggleGrid,
+        Action::ToggleLayers,
+        Action::ToggleTemplates,
+        Action::ToggleQueue,
+        Action::Help,
+    ];
+
+    /// Name used for the action in the `[keys]` config table
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::ToolMove => "move",
+            Action::ToolBrush => "brush",
+            Action::ToolPicker => "picker",
+            Action::ToolPlacer => "placer",
+            Action::ToolLine => "line",
+            Action::ToolRectangle => "rectangle",
+            Action::ToolFill => "fill",
+            Action::ConfirmShape => "confirm_shape",
+            Action::CancelShape => "cancel_shape",
+            Action::ZoomIn => "zoom_in",
+            Action::ZoomOut => "zoom_out",
+            Action::PanLeft => "pan_left",
+            Action::PanRight => "pan_right",
+            Action::PanUp => "pan_up",
+            Action::PanDown => "pan_down",
+            Action::FitWindow => "fit_window",
+            Action::GoTo => "go_to",
+            Action::Undo => "undo",
+            Action::ToggleOverlays => "toggle_overlays",
+            Action::ToggleDiff => "toggle_diff",
+            Action::ToggleGrid => "toggle_grid",
+            Action::ToggleLayers => "toggle_layers",
+            Action::ToggleTemplates => "toggle_templates",
+            Action::ToggleQueue => "toggle_queue",
+            Action::Help => "help",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::ToolMove => "move tool",
+            Action::ToolBrush => "brush tool",
+            Action::ToolPicker => "picker tool",
+            Action::ToolPlacer => "placer tool",
+            Action::ToolLine => "line tool",
+            Action::ToolRectangle => "rectangle tool",
+            Action::ToolFill => "flood fill tool",
+            Action::ConfirmShape => "place the previewed shape",
+            Action::CancelShape => "discard the previewed shape",
+            Action::ZoomIn => "zoom in",
+            Action::ZoomOut => "zoom out",
+            Action::PanLeft => "pan left",
+            Action::PanRight => "pan right",
+            Action::PanUp => "pan up",
+            Action::PanDown => "pan down",
+            Action::FitWindow => "fit the canvas to the window",
+            Action::GoTo => "go to coordinates",
+            Action::Undo => "undo the last brush pixel",
+            Action::ToggleOverlays => "show or hide overlays",
+            Action::ToggleDiff => "show or hide the diff",
+            Action::ToggleGrid => "show or hide the pixel grid",
+            Action::ToggleLayers => "layers window",
+            Action::ToggleTemplates => "templates window",
+            Action::ToggleQueue => "queue window",
+            Action::Help => "shortcuts",
+        }
+    }
+
+    fn default_chords(&self) -> &'static str {
+        match self {
+            Action::ToolMove => "M",
+            Action::ToolBrush => "B",
+            Action::ToolPicker => "I",
+            Action::ToolPlacer => "P",
+            Action::ToolLine => "N",
+            Action::ToolRectangle => "R",
+            Action::ToolFill => "shift+F",
+            Action::ConfirmShape => "Enter",
+            Action::CancelShape => "Escape",
+            Action::ZoomIn => "=",
+            Action::ZoomOut => "-",
+            Action::PanLeft => "Left, A",
+            Action::PanRight => "Right, D",
+            Action::PanUp => "Up, W",
+            Action::PanDown => "Down, S",
+            Action::FitWindow => "F",
+            Action::GoTo => "G",
+            Action::Undo => "ctrl+Z",
+            Action::ToggleOverlays => "O",
+            Action::ToggleDiff => "shift+O",
+            Action::ToggleGrid => "shift+G",
+            Action::ToggleLayers => "L",
+            Action::ToggleTemplates => "T",
+            Action::ToggleQueue => "Q",
+            Action::Help => "F1",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+impl Chord {
+    /// Parses `+` separated modifiers followed by a key or `mouse_left/right/middle`
+    pub fn parse(value: &str) -> Option<Chord> {
+        let mut parts: Vec<String> = value.split('+').map(|part| part.trim().to_lowercase()).collect();
+        let input = match parts.pop()?.as_str() {
+            "mouse_left" => Input::Mouse(MouseButton::Left),
+            "mouse_right" => Input::Mouse(MouseButton::Right),
+            "mouse_middle" => Input::Mouse(MouseButton::Middle),
+            key => Input::Key(parse_key(key)?),
+        };
+
+        let modifiers = parts
+            .iter()
+            .map(|part| MODIFIERS.iter().find(|(name, ..)| name == part).map(|(name, ..)| *name))
+            .collect::<Option<Vec<&'static str>>>()?;
+
+        Some(Chord {
+            modifiers,
+            input,
+        })
+    }
+
+    pub fn is_pressed(&self) -> bool {
+        self.modifiers_held() && match self.input {
+            Input::Key(key) => is_key_pressed(key),
+            Input::Mouse(button) => is_mouse_button_pressed(button),
+        }
+    }
+
+    pub fn is_down(&self) -> bool {
+        self.modifiers_held() && match self.input {
+            Input::Key(key) => is_key_down(key),
+            Input::Mouse(button) => is_mouse_button_down(button),
+        }
+    }
+
+    /// Exactly the chord's modifiers have to be held so `ctrl+z` doesn't trigger `z`
+    fn modifiers_held(&self) -> bool {
+        MODIFIERS.iter().all(|(name, left, right)| {
+            self.modifiers.contains(name) == (is_key_down(*left) || is_key_down(*right))
+        })
+    }
+}
+
+impl Keymap {
+    /// Builds the defaults and replaces the actions present in the config table,
+    /// values hold one or more chords separated by `,`
+    pub fn new(keys: &BTreeMap<String, String>) -> Self {
+        let mut bindings: Vec<(Action, Chord)> = Action::ALL
+            .iter()
+            .filter(|action| !keys.contains_key(action.name()))
+            .flat_map(|action| parse_chords(*action, action.default_chords()))
+            .collect();
+
+        for (name, chords) in keys {
+            match Action::from_name(name) {
+                Some(action) => bindings.extend(parse_chords(action, chords)),
+                None => println!("unknown action {name} in the key bindings"),
+            }
+        }
+
+        Self { bindings }
+    }
+
+    pub fn is_pressed(&self, action: Action) -> bool {
+        self.chords(action).any(Chord::is_pressed)
+    }
+
+    pub fn is_down(&self, action: Action) -> bool {
+        self.chords(action).any(Chord::is_down)
+    }
+
+    pub fn chords(&self, action: Action) -> impl Iterator<Item = &Chord> {
+        self.bindings
+            .iter()
+            .filter(move |(bound, _)| *bound == action)
+            .map(|(_, chord)| chord)
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new(&BTreeMap::new())
+    }
+}
+
+impl Display for Chord {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for modifier in self.modifiers.iter() {
+            write!(f, "{modifier}+")?;
+        }
+        match self.input {
+            Input::Key(key) => write!(f, "{key:?}"),
+            Input::Mouse(button) => write!(f, "mouse {button:?}"),
+        }
+    }
+}
+
+fn parse_chords(action: Action, chords: &str) -> Vec<(Action, Chord)> {
+    chords
+        .split(',')
+        .filter(|chord| !chord.trim().is_empty())
+        .filter_map(|chord| match Chord::parse(chord) {
+            Some(chord) => Some((action, chord)),
+            None => {
+                println!("couldn't parse key binding {chord} for {}", action.name());
+                None
+            }
+        })
+        .collect()
+}
+
+/// Parses key names like `B`, `5`, `F1`, `Space` or `Left`
+fn parse_key(name: &str) -> Option<KeyCode> {
+    let key = match name {
+        "a" => KeyCode::A, "b" => KeyCode::B, "c" => KeyCode::C, "d" => KeyCode::D,
+        "e" => KeyCode::E, "f" => KeyCode::F, "g" => KeyCode::G, "h" => KeyCode::H,
+        "i" => KeyCode::I, "j" => KeyCode::J, "k" => KeyCode::K, "l" => KeyCode::L,
+        "m" => KeyCode::M, "n" => KeyCode::N, "o" => KeyCode::O, "p" => KeyCode::P,
+        "q" => KeyCode::Q, "r" => KeyCode::R, "s" => KeyCode::S, "t" => KeyCode::T,
+        "u" => KeyCode::U, "v" => KeyCode::V, "w" => KeyCode::W, "x" => KeyCode::X,
+        "y" => KeyCode::Y, "z" => KeyCode::Z,
+        "0" => KeyCode::Key0, "1" => KeyCode::Key1, "2" => KeyCode::Key2, "3" => KeyCode::Key3,
+        "4" => KeyCode::Key4, "5" => KeyCode::Key5, "6" => KeyCode::Key6, "7" => KeyCode::Key7,
+        "8" => KeyCode::Key8, "9" => KeyCode::Key9,
+        "f1" => KeyCode::F1, "f2" => KeyCode::F2, "f3" => KeyCode::F3, "f4" => KeyCode::F4,
+        "f5" => KeyCode::F5, "f6" => KeyCode::F6, "f7" => KeyCode::F7, "f8" => KeyCode::F8,
+        "f9" => KeyCode::F9, "f10" => KeyCode::F10, "f11" => KeyCode::F11, "f12" => KeyCode::F12,
+        "space" => KeyCode::Space,
+        "enter" => KeyCode::Enter,
+        "escape" | "esc" => KeyCode::Escape,
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "delete" => KeyCode::Delete,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "minus" | "-" => KeyCode::Minus,
+        "equal" | "=" => KeyCode::Equal,
+        "slash" | "/" => KeyCode::Slash,
+        _ => return None,
+    };
+    Some(key)
+}
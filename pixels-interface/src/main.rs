@@ -1,31 +1,82 @@
-use bevy_time::Time;
-use clap::Parser;
+use std::error::Error;
+use std::io::{
+    self,
+    Write
+};
 
-use rfd::{
-    FileDialog,
-    MessageDialog,
-    MessageButtons
+use bevy_time::Time;
+use clap::{
+    Parser,
+    Subcommand
 };
 
 use bevy_ecs::prelude::*;
 use macroquad::prelude::*;
 use pixels_canvas::prelude::*;
+use pixels_util::prelude::*;
 
 use canvas::CanvasContainer;
+use config::Config;
+use keymap::Keymap;
 use state::{
     State,
     ToolType
 };
 
 mod canvas;
+mod cli;
+mod config;
 mod input;
+mod keymap;
 mod panel;
 mod state;
 
 #[derive(Parser)]
 pub struct Args {
-    /// Todo: Refresh token to connect the API
-    refresh: String,
+    /// Refresh token to connect the API, read from the config when omitted
+    #[arg(env = "PIXELS_TOKEN")]
+    refresh: Option<String>,
+
+    /// Base url of the pixels server to connect
+    #[arg(long, env = "PIXELS_SERVER", global = true)]
+    server: Option<String>,
+
+    /// Palette preset or palette file (.gpl or hex list) to quantize imported templates with
+    #[arg(long, global = true)]
+    palette: Option<String>,
+
+    /// Dithering used while quantizing: none, floyd-steinberg or ordered
+    #[arg(long, default_value = "none", global = true)]
+    dither: Dither,
+
+    /// Runs a command instead of opening the window
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Opens the window, the default without a command
+    Gui,
+    /// Saves the canvas as a png file
+    #[command(visible_alias = "snapshot")]
+    Export(cli::ExportArgs),
+    /// Places a single pixel
+    Place(cli::PlaceArgs),
+    /// Works with templates without opening the window
+    #[command(subcommand)]
+    Template(cli::TemplateCommand),
+    /// Prints the canvas size and the cooldown cached by the last placement
+    Status,
+    /// Records a timelapse of the canvas until interrupted
+    Record(cli::RecordArgs),
+    /// Renders a timelapse recording to a gif or png frames
+    Render(cli::RenderArgs),
+    /// Stores the refresh token in the config directory
+    Login {
+        /// Read from stdin when omitted, which keeps it out of the shell history
+        token: Option<String>,
+    },
 }
 
 struct App {
@@ -35,15 +86,106 @@ struct App {
 }
 
 fn main() {
-    let element = get_element();
-    macroquad::Window::new("Pixels Client", entry(element));
+    let mut args = Args::parse();
+    let config = Config::load().unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    });
+
+    args.server = args.server.take().or_else(|| config.server.clone());
+    args.refresh = args.refresh.take().or_else(|| config.token());
+
+    let result = match &args.command {
+        Some(Command::Export(export)) => cli::export(&args, export),
+        Some(Command::Place(place)) => cli::place(&args, place),
+        Some(Command::Template(command)) => cli::template(&args, command),
+        Some(Command::Status) => cli::status(&args),
+        Some(Command::Record(record)) => cli::record(&args, record),
+        Some(Command::Render(render)) => cli::render(render),
+        Some(Command::Login { token }) => login(&args, token.clone()),
+        Some(Command::Gui) | None => gui(args, config),
+    };
+
+    if let Err(e) = result {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+}
+
+fn gui(args: Args, config: Config) -> Result<(), Box<dyn Error>> {
+    let window = Conf {
+        window_title: String::from("Pixels Client"),
+        window_width: config.window.width.unwrap_or(800) as i32,
+        window_height: config.window.height.unwrap_or(600) as i32,
+        fullscreen: config.window.fullscreen,
+        ..Default::default()
+    };
+
+    load_palette(&args)?;
+    let mut state = State::new(args.palette.clone(), args.dither);
+    if let Some(color) = config.color() {
+        state.color = color.into();
+    }
+    state.keymap = Keymap::new(&config.keys);
+    state.config = config;
+
+    macroquad::Window::from_config(window, entry(args, state));
+    Ok(())
+}
+
+fn load_palette(args: &Args) -> io::Result<Option<Palette>> {
+    args.palette
+        .as_ref()
+        .map(|name| Palette::load(name).map_err(|e| {
+            io::Error::new(e.kind(), format!("couldn't load palette {name}: {e}"))
+        }))
+        .transpose()
+}
+
+fn credentials(args: &Args) -> Result<(ClientConfig, String), Box<dyn Error>> {
+    let config = args.server
+        .clone()
+        .map(ClientConfig::new)
+        .unwrap_or_default();
+    let refresh = args.refresh
+        .clone()
+        .ok_or("no refresh token, pass one or store it with the login command")?;
+
+    Ok((config, refresh))
+}
+
+fn connect(args: &Args) -> Result<Canvas, Box<dyn Error>> {
+    let (config, refresh) = credentials(args)?;
+    Ok(Canvas::new(config, refresh)?)
+}
+
+fn login(args: &Args, token: Option<String>) -> Result<(), Box<dyn Error>> {
+    let token = match token {
+        Some(token) => token,
+        None => {
+            print!("refresh token: ");
+            io::stdout().flush()?;
+
+            let mut token = String::new();
+            io::stdin().read_line(&mut token)?;
+            token
+        }
+    };
+    let token = token.trim().to_string();
+
+    let mut client = Client::new(args.server
+        .clone()
+        .map(ClientConfig::new)
+        .unwrap_or_default());
+    client.auth(token.clone())?;
+
+    let path = config::save_token(&token)?;
+    println!("saved refresh token to {}", path.display());
+    Ok(())
 }
 
-async fn entry(image: Option<Element>) {
-    let mut app = App::new(
-        Args::parse(),
-        State::new(image)
-    );
+async fn entry(args: Args, state: State) {
+    let mut app = App::new(args, state);
 
     loop {
         app.update();
@@ -55,14 +197,18 @@ async fn entry(image: Option<Element>) {
 
 impl App {
     fn new(args: Args, mut state: State) -> Self {
-        let canvas = Canvas::new(args.refresh).expect("couldn't create canvas");
+        let mut canvas = connect(&args)
+            .unwrap_or_else(|e| panic!("couldn't create canvas: {e}"));
+        *canvas.queue_mut() = state::load_queue();
         let mut world = World::new();
 
-        request_new_screen_size(
-            (canvas.width() * 2) as f32,
-            (canvas.height() * 2) as f32
-        );
-        state.camera_state.position = calculate_center(&canvas);
+        if state.config.window.width.is_none() && state.config.window.height.is_none() {
+            request_new_screen_size(
+                (canvas.width() * 2) as f32,
+                (canvas.height() * 2) as f32
+            );
+        }
+        state.camera_state.jump_to(calculate_center(&canvas));
 
         let mut draw_schedule = Schedule::default();
         let mut update_schedule = Schedule::default();
@@ -106,6 +252,14 @@ pub fn update_time(mut time: ResMut<Time>) {
 }
 
 pub fn update_camera(mut state: ResMut<State>) {
+    let smoothing = state.config.camera.smoothing;
+    let amount = if smoothing > 0.0 {
+        1.0 - (-smoothing * get_frame_time()).exp()
+    } else {
+        1.0
+    };
+    state.camera_state.ease(amount);
+
     state.camera_state.instance = Camera2D {
         target: state.camera_state.position,
         zoom: calculate_zoom(state.camera_state.zoom),
@@ -129,19 +283,13 @@ pub fn mouse_world_pos(camera: Camera2D) -> Vec2 {
     camera.screen_to_world(vec2(mouse_position().0, mouse_position().1))
 }
 
-fn get_element() -> Option<Element> {
-    let select = MessageDialog::new()
-        .set_buttons(MessageButtons::YesNo)
-        .set_description("would you like to select an image to paste?")
-        .show();
-    if !select {
-        return None
-    }
+/// The canvas pixel under the mouse, none when the mouse is off the canvas
+pub fn hovered_pixel(camera: Camera2D, canvas: &Canvas) -> Option<(u32, u32)> {
+    let pos = mouse_world_pos(camera);
+    let inside = pos.x >= 0.0
+        && pos.y >= 0.0
+        && pos.x < canvas.width() as f32
+        && pos.y < canvas.height() as f32;
 
-    let path = FileDialog::new()
-        .add_filter("PNG Image", &["png"])
-        .add_filter("JPEG Image", &["jpg", "jpeg"])
-        .set_directory("~")
-        .pick_file();
-    path.map(Element::new)
+    inside.then_some((pos.x as u32, pos.y as u32))
 }
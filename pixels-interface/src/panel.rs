@@ -1,18 +1,48 @@
+use std::hash::Hash;
+
 use egui_macroquad::egui::{self, Response, TextureId, Widget, Vec2, Ui, Context, show_tooltip_at_pointer, Id};
+use rfd::FileDialog;
 
 use bevy_ecs::prelude::*;
+use pixels_canvas::prelude::*;
+use pixels_util::prelude::*;
 
 use super::{
+    canvas::CanvasContainer,
     State,
     ToolType
 };
 
+use crate::input::confirm_shape;
+use crate::state::save_queue;
+use crate::keymap::Action;
+use macroquad::prelude::{
+    screen_height,
+    screen_width,
+    vec2
+};
 use crate::{
     panel,
     tool_button,
     tool_button_if
 };
 
+enum QueueAction {
+    Raise(usize),
+    Lower(usize),
+    Remove(usize),
+    Clear,
+}
+
+enum LayerAction {
+    Raise(String),
+    Lower(String),
+    Remove(String),
+}
+
+/// Zoom used by "go to" when the camera is further out
+const GOTO_ZOOM: f32 = 16.0;
+
 struct ToolButton {
     selected: bool,
     icon: TextureId,
@@ -20,7 +50,7 @@ struct ToolButton {
 }
 
 pub fn draw(world: &mut World) {
-    panel!(world, |ctx: &Context, ui: &mut Ui, state: &mut State| {
+    panel!(world, |ctx: &Context, ui: &mut Ui, state: &mut State, canvas: &mut Canvas| {
         ui.add_space(20.0);
         ui.color_edit_button_rgb(&mut state.color);
 
@@ -38,10 +68,466 @@ pub fn draw(world: &mut World) {
 
         tool_button_if!(ctx, ui, state, ToolType::Placer, state.menu_state.image_icon, {
             state.selected_tool = ToolType::Placer;
-        }, state.image.is_some());
+        }, state.selected_template().is_some());
+
+        tool_button!(ctx, ui, state, ToolType::Line, state.menu_state.line_icon, {
+            state.selected_tool = ToolType::Line;
+        });
+
+        tool_button!(ctx, ui, state, ToolType::Rectangle, state.menu_state.rectangle_icon, {
+            state.selected_tool = ToolType::Rectangle;
+        });
+
+        tool_button!(ctx, ui, state, ToolType::Fill, state.menu_state.fill_icon, {
+            state.selected_tool = ToolType::Fill;
+        });
+
+        if state.selected_tool == ToolType::Rectangle {
+            ui.checkbox(&mut state.fill_rectangle, "filled");
+        }
+
+        if !state.shape.is_empty() {
+            ui.add_space(20.0);
+            ui.label(format!("{} pixels", state.shape.len()));
+
+            if ui.button("place").clicked() {
+                confirm_shape(state, canvas);
+            }
+
+            if ui.button("discard").clicked() {
+                state.shape.clear();
+            }
+        }
+
+        if state.placer.is_some() {
+            ui.add_space(20.0);
+
+            if let Some(diff) = &state.diff {
+                ui.label(format!("{:.1}% done", diff.completion()));
+                ui.label(format!("{} wrong", diff.mismatching));
+            }
+            ui.checkbox(&mut state.show_diff, "diff");
+
+            if ui.button("stop").clicked() {
+                if let Some(placer) = state.set_placer(None) {
+                    placer.cancel(canvas);
+                }
+            }
+        }
+
+        ui.add_space(20.0);
+        if ui.button("import").clicked() {
+            import_image(state);
+        }
+
+        if ui.selectable_label(state.show_layers, "layers").clicked() {
+            state.show_layers = !state.show_layers;
+        }
+
+        if ui.selectable_label(state.show_templates, "templates").clicked() {
+            state.show_templates = !state.show_templates;
+        }
+
+        if ui.selectable_label(state.show_queue, format!("queue {}", canvas.queue().len())).clicked() {
+            state.show_queue = !state.show_queue;
+        }
+
+        if ui.selectable_label(state.show_export, "export").clicked() {
+            state.show_export = !state.show_export;
+        }
+
+        if ui.selectable_label(state.show_help, "?").clicked() {
+            state.show_help = !state.show_help;
+        }
+
+        if state.show_layers {
+            draw_layers(ctx, state, canvas);
+        }
+
+        if state.show_help {
+            draw_help(ctx, state);
+        }
+
+        if state.show_export {
+            draw_export(ctx, state, canvas);
+        }
+
+        if state.show_templates {
+            draw_templates(ctx, state);
+        }
+
+        if state.show_goto {
+            draw_goto(ctx, state, canvas);
+        }
+
+        if state.show_queue {
+            draw_queue(ctx, state, canvas);
+        }
     });
 }
 
+fn draw_queue(ctx: &Context, state: &mut State, canvas: &mut Canvas) {
+    let mut action = None;
+
+    egui::Window::new("queue")
+        .resizable(false)
+        .open(&mut state.show_queue)
+        .show(ctx, |ui| {
+            let queue = canvas.queue();
+            ui.label(format!("{} pixels queued", queue.len()));
+
+            let entries: Vec<(u32, u32, pixels_util::color::Color)> = queue.iter().copied().collect();
+            let row_height = ui.spacing().interact_size.y;
+            egui::ScrollArea::vertical().max_height(300.0).show_rows(ui, row_height, entries.len(), |ui, rows| {
+                for index in rows {
+                    let (x, y, color) = entries[index];
+                    let (r, g, b) = color.to_rgb();
+
+                    ui.horizontal(|ui| {
+                        egui::color_picker::show_color(ui, egui::Color32::from_rgb(r, g, b), Vec2::splat(12.0));
+                        ui.label(format!("{x}, {y}"));
+
+                        if ui.small_button("up").clicked() {
+                            action = Some(QueueAction::Raise(index));
+                        }
+                        if ui.small_button("down").clicked() {
+                            action = Some(QueueAction::Lower(index));
+                        }
+                        if ui.small_button("cancel").clicked() {
+                            action = Some(QueueAction::Remove(index));
+                        }
+                    });
+                }
+            });
+
+            if !entries.is_empty() && ui.button("clear").clicked() {
+                action = Some(QueueAction::Clear);
+            }
+        });
+
+    let Some(action) = action else {
+        return;
+    };
+
+    let queue = canvas.queue_mut();
+    match action {
+        QueueAction::Raise(index) => {
+            queue.move_entry(index, index.saturating_sub(1));
+        }
+        QueueAction::Lower(index) => {
+            queue.move_entry(index, index + 1);
+        }
+        QueueAction::Remove(index) => {
+            queue.remove(index);
+        }
+        QueueAction::Clear => queue.clear(),
+    }
+    save_queue(queue);
+}
+
+/// Shows the hovered pixel with its color and the zoom
+fn draw_status(ctx: &Context, state: &State, canvas: &Canvas) {
+    egui::TopBottomPanel::bottom("status").show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            match super::hovered_pixel(state.camera_state.instance, canvas) {
+                Some((x, y)) => {
+                    ui.label(format!("{x}, {y}"));
+
+                    if let Some(color) = canvas.get_pixel(x, y) {
+                        let (r, g, b) = color.to_rgb();
+                        egui::color_picker::show_color(ui, egui::Color32::from_rgb(r, g, b), Vec2::splat(12.0));
+                        ui.label(format!("#{}", color.to_hex(ColorMode::RGB)));
+                    }
+                }
+                None => {
+                    ui.label("off canvas");
+                }
+            }
+
+            ui.separator();
+            ui.label(format!("{:.1}x", state.camera_state.zoom));
+        });
+    });
+}
+
+fn draw_goto(ctx: &Context, state: &mut State, canvas: &Canvas) {
+    egui::Window::new("go to")
+        .resizable(false)
+        .open(&mut state.show_goto)
+        .show(ctx, |ui| {
+            let (x, y) = &mut state.menu_state.goto;
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(x).prefix("x ").clamp_range(0..=canvas.width() - 1));
+                ui.add(egui::DragValue::new(y).prefix("y ").clamp_range(0..=canvas.height() - 1));
+            });
+
+            let camera = &mut state.camera_state;
+            let config = &state.config.camera;
+            ui.horizontal(|ui| {
+                if ui.button("go").clicked() {
+                    let center = vec2(screen_width(), screen_height()) / 2.0;
+                    camera.zoom_at(camera.target_zoom.max(GOTO_ZOOM), center, config);
+                    camera.look_at(vec2(*x as f32 + 0.5, *y as f32 + 0.5));
+                }
+
+                if ui.button("fit").clicked() {
+                    camera.fit(vec2(canvas.width() as f32, canvas.height() as f32), config);
+                }
+            });
+        });
+}
+
+fn draw_help(ctx: &Context, state: &mut State) {
+    egui::Window::new("shortcuts")
+        .resizable(false)
+        .open(&mut state.show_help)
+        .show(ctx, |ui| {
+            egui::Grid::new("shortcuts").striped(true).show(ui, |ui| {
+                for action in Action::ALL {
+                    let chords: Vec<String> = state.keymap
+                        .chords(action)
+                        .map(|chord| chord.to_string())
+                        .collect();
+
+                    ui.label(action.description());
+                    ui.label(if chords.is_empty() { String::from("unbound") } else { chords.join(", ") });
+                    ui.end_row();
+                }
+            });
+        });
+}
+
+fn draw_export(ctx: &Context, state: &mut State, canvas: &Canvas) {
+    egui::Window::new("export")
+        .resizable(false)
+        .open(&mut state.show_export)
+        .show(ctx, |ui| {
+            let config = &mut state.export;
+            ui.checkbox(&mut config.merged, "overlays");
+
+            let mut crop = config.region.is_some();
+            if ui.checkbox(&mut crop, "crop").changed() {
+                config.region = crop.then(|| Region::from_size(canvas.size()));
+            }
+
+            if let Some(region) = config.region.as_mut() {
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut region.x).prefix("x ").clamp_range(0..=canvas.width()));
+                    ui.add(egui::DragValue::new(&mut region.y).prefix("y ").clamp_range(0..=canvas.height()));
+                    ui.add(egui::DragValue::new(&mut region.width).prefix("w ").clamp_range(1..=canvas.width()));
+                    ui.add(egui::DragValue::new(&mut region.height).prefix("h ").clamp_range(1..=canvas.height()));
+                });
+            }
+
+            ui.add(egui::DragValue::new(&mut config.scale).prefix("scale ").clamp_range(1..=32));
+
+            if !ui.button("save").clicked() {
+                return;
+            }
+
+            let path = FileDialog::new()
+                .add_filter("PNG Image", &["png"])
+                .set_file_name("canvas.png")
+                .save_file();
+
+            if let Some(path) = path {
+                match canvas.export(config).save(&path) {
+                    Ok(()) => println!("saved canvas to {}", path.display()),
+                    Err(e) => println!("couldn't save canvas: {e}"),
+                }
+            }
+        });
+}
+
+fn import_image(state: &mut State) {
+    let path = FileDialog::new()
+        .add_filter("Image", &["png", "jpg", "jpeg", "gif", "webp", "bmp"])
+        .add_filter("PNG Image", &["png"])
+        .add_filter("JPEG Image", &["jpg", "jpeg"])
+        .set_directory("~")
+        .pick_file();
+
+    if let Some(path) = path {
+        state.add_template(path);
+    }
+}
+
+fn draw_templates(ctx: &Context, state: &mut State) {
+    let mut changed = false;
+    let mut removed = None;
+
+    // the contents import into the whole state, so the flag is copied out while the window shows
+    let mut open = state.show_templates;
+    egui::Window::new("templates")
+        .resizable(false)
+        .open(&mut open)
+        .show(ctx, |ui| {
+            let selected = state.selected;
+
+            for (id, template) in state.templates.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.selectable_label(selected == Some(id), template.name()).clicked() {
+                        state.selected = Some(id);
+                    }
+
+                    let mut visible = template.is_visible();
+                    if ui.checkbox(&mut visible, "show").changed() {
+                        template.set_visible(visible);
+                        changed = true;
+                    }
+
+                    let mut locked = template.is_locked();
+                    if ui.checkbox(&mut locked, "lock").changed() {
+                        template.set_locked(locked);
+                        changed = true;
+                    }
+
+                    let mut opacity = template.get_opacity();
+                    if ui.add(egui::Slider::new(&mut opacity, 0.0..=1.0)).changed() {
+                        template.set_opacity(opacity);
+                        changed = true;
+                    }
+
+                    let mut palette = template.get_palette().map(String::from);
+                    let mut dither = template.get_dither();
+                    if palette_picker(ui, ("template", id), &mut palette, &mut dither) {
+                        match template.set_palette(palette.as_deref(), dither) {
+                            Ok(()) => changed = true,
+                            Err(e) => println!("couldn't quantize {}: {e}", template.name()),
+                        }
+                    }
+
+                    let (x, y) = template.get_position();
+                    ui.label(format!("{x}, {y}"));
+
+                    if ui.small_button("remove").clicked() {
+                        removed = Some(id);
+                    }
+                });
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("add").clicked() {
+                    import_image(state);
+                }
+                palette_picker(ui, "import", &mut state.palette, &mut state.dither);
+            });
+        });
+    state.show_templates = open;
+
+    if let Some(id) = removed {
+        state.remove_template(id);
+    } else if changed {
+        state.save_templates();
+    }
+}
+
+/// Picks a palette preset and dithering, a palette file chosen earlier stays an option.
+/// Returns whether either changed.
+fn palette_picker(ui: &mut Ui, id: impl Hash, palette: &mut Option<String>, dither: &mut Dither) -> bool {
+    let previous = (palette.clone(), *dither);
+    let mut options: Vec<Option<String>> = std::iter::once(None)
+        .chain(Palette::presets().map(|preset| Some(preset.to_string())))
+        .collect();
+    if !options.contains(palette) {
+        options.push(palette.clone());
+    }
+
+    let id = Id::new(id);
+    egui::ComboBox::from_id_source(id.with("palette"))
+        .selected_text(palette.as_deref().unwrap_or("no palette"))
+        .show_ui(ui, |ui| {
+            for option in options {
+                let text = option.as_deref().unwrap_or("no palette").to_string();
+                ui.selectable_value(palette, option, text);
+            }
+        });
+    egui::ComboBox::from_id_source(id.with("dither"))
+        .selected_text(dither.name())
+        .show_ui(ui, |ui| {
+            for mode in Dither::ALL {
+                ui.selectable_value(dither, mode, mode.name());
+            }
+        });
+
+    (palette.clone(), *dither) != previous
+}
+
+fn draw_layers(ctx: &Context, state: &mut State, canvas: &mut Canvas) {
+    let mut action = None;
+
+    egui::Window::new("layers")
+        .resizable(false)
+        .open(&mut state.show_layers)
+        .show(ctx, |ui| {
+            for layer in canvas.layers_mut().iter_mut().rev() {
+                let name = layer.name().to_string();
+
+                ui.horizontal(|ui| {
+                    let mut visible = layer.is_visible();
+                    if ui.checkbox(&mut visible, &name).changed() {
+                        layer.set_visible(visible);
+                    }
+
+                    let mut opacity = layer.get_opacity();
+                    if ui.add(egui::Slider::new(&mut opacity, 0.0..=1.0)).changed() {
+                        layer.set_opacity(opacity);
+                    }
+
+                    let mut blend = layer.get_blend();
+                    egui::ComboBox::from_id_source(&name)
+                        .selected_text(blend.name())
+                        .show_ui(ui, |ui| {
+                            for mode in BlendMode::ALL {
+                                ui.selectable_value(&mut blend, mode, mode.name());
+                            }
+                        });
+                    layer.set_blend(blend);
+
+                    if ui.small_button("up").clicked() {
+                        action = Some(LayerAction::Raise(name.clone()));
+                    }
+                    if ui.small_button("down").clicked() {
+                        action = Some(LayerAction::Lower(name.clone()));
+                    }
+                    if name != MAIN_LAYER && name != IMAGE_LAYER && ui.small_button("remove").clicked() {
+                        action = Some(LayerAction::Remove(name.clone()));
+                    }
+                });
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut state.menu_state.layer_name);
+
+                let name = state.menu_state.layer_name.trim().to_string();
+                if ui.button("add").clicked() && !name.is_empty() {
+                    if canvas.add_layer(&name, 1.0).is_some() {
+                        state.menu_state.layer_name.clear();
+                    } else {
+                        println!("layer {name} already exists");
+                    }
+                }
+            });
+        });
+
+    match action {
+        Some(LayerAction::Raise(name)) => {
+            let index = canvas.layer_index(&name).unwrap_or_default();
+            canvas.move_layer(&name, index + 1);
+        }
+        Some(LayerAction::Lower(name)) => {
+            let index = canvas.layer_index(&name).unwrap_or_default();
+            canvas.move_layer(&name, index.saturating_sub(1));
+        }
+        Some(LayerAction::Remove(name)) => {
+            canvas.remove_layer(&name);
+        }
+        None => {}
+    }
+}
+
 impl ToolButton {
     fn new(selected: bool, icon: TextureId, size: Vec2) -> Self {
         Self {
@@ -71,25 +557,31 @@ impl Widget for ToolButton {
 #[macro_export]
 macro_rules! panel {
     ($world:expr, $body:expr $(,)?) => {
-        let mut res = $world.get_resource_mut::<State>().unwrap();
-        let mut state = res.as_mut();
+        $world.resource_scope(|world, mut container: Mut<CanvasContainer>| {
+            let mut res = world.get_resource_mut::<State>().unwrap();
+            let mut state = res.as_mut();
+            let canvas = &mut container.canvas;
 
-        egui_macroquad::ui(|ctx| {
-            if state.cooldown != 0.0 {
-                show_tooltip_at_pointer(ctx, Id::new("cooldown"), |ui| {
-                    ui.label(format!("please wait {} secs", state.cooldown.round()));
-                });
-            }
+            egui_macroquad::ui(|ctx| {
+                if state.cooldown != 0.0 {
+                    show_tooltip_at_pointer(ctx, Id::new("cooldown"), |ui| {
+                        ui.label(format!("please wait {} secs", state.cooldown.round()));
+                    });
+                }
+
+                draw_status(ctx, state, canvas);
 
-            let panel = egui::SidePanel::left("settings").show(ctx, |ui| {
-                ui.vertical_centered(|ui| {
-                    ui.set_width(0.0);
-                    $body(ctx, ui, &mut state)
+                let panel = egui::SidePanel::left("settings").show(ctx, |ui| {
+                    ui.vertical_centered(|ui| {
+                        ui.set_width(0.0);
+                        $body(ctx, ui, &mut state, canvas)
+                    });
                 });
-            });
 
-            state.focus = ctx.is_pointer_over_area();
-            state.menu_state.area = panel.response.rect;
+                state.focus = ctx.is_pointer_over_area();
+                state.typing = ctx.wants_keyboard_input();
+                state.menu_state.area = panel.response.rect;
+            });
         });
 
         egui_macroquad::draw();
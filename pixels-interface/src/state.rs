@@ -1,34 +1,87 @@
+use std::fs;
+use std::path::{
+    Path,
+    PathBuf
+};
+use std::time::{
+    SystemTime,
+    UNIX_EPOCH
+};
+
 use egui_macroquad::egui::Rect;
 use egui_extras::RetainedImage;
 
 use bevy_ecs::prelude::*;
 use macroquad::prelude::*;
 use pixels_canvas::prelude::*;
+use pixels_util::prelude::*;
+
+use crate::config::{
+    CameraConfig,
+    Config
+};
+use crate::keymap::Keymap;
 
 #[derive(Resource)]
 pub struct State {
     pub focus: bool,
+    pub typing: bool,
     pub color: [f32; 3],
     pub cooldown: f32,
-    pub image: Option<Element>,
+    /// Set once the server revoked the refresh token, nothing is placed anymore
+    pub revoked: bool,
+    pub templates: Templates,
+    pub selected: Option<usize>,
+    /// Palette and dithering newly imported templates start with
+    pub palette: Option<String>,
+    pub dither: Dither,
+    pub placer: Option<Placer>,
+    /// Changes whenever the placer is replaced, the diff is recomputed then
+    pub placer_revision: u64,
+    pub diff: Option<Diff>,
+    pub show_diff: bool,
+    pub show_grid: bool,
+    pub show_layers: bool,
+    pub show_templates: bool,
+    pub show_export: bool,
+    pub show_help: bool,
+    pub show_goto: bool,
+    pub show_queue: bool,
+    pub history: Vec<(u32, u32, pixels_util::color::Color)>,
+    /// Corner the line or rectangle being dragged starts at
+    pub shape_origin: Option<(u32, u32)>,
+    /// Pixels of the shape waiting to be confirmed
+    pub shape: Vec<(u32, u32)>,
+    pub fill_rectangle: bool,
+    pub export: ExportConfig,
     pub selected_tool: ToolType,
+    pub config: Config,
+    pub keymap: Keymap,
     pub camera_state: CameraState,
     pub menu_state: MenuState,
 }
 
+/// The camera eases from `zoom` and `position` towards their targets every frame
 pub struct CameraState {
     pub zoom: f32,
+    pub target_zoom: f32,
     pub instance: Camera2D,
     pub position: Vec2,
+    pub target_position: Vec2,
     pub move_origin: Vec2,
 }
 
 pub struct MenuState {
     pub area: Rect,
+    pub layer_name: String,
+    pub goto: (u32, u32),
     pub move_icon: RetainedImage,
     pub brush_icon: RetainedImage,
     pub image_icon: RetainedImage,
     pub picker_icon: RetainedImage,
+    pub line_icon: RetainedImage,
+    pub rectangle_icon: RetainedImage,
+    pub fill_icon: RetainedImage,
 }
 
 #[derive(PartialEq, Eq, Debug)]
@@ -37,28 +90,220 @@ pub enum ToolType {
     Brush,
     Picker,
     Placer,
+    Line,
+    Rectangle,
+    Fill,
 }
 
 impl State {
-    pub fn new(image: Option<Element>) -> Self {
+    pub fn new(palette: Option<String>, dither: Dither) -> Self {
+        let templates = load_templates();
+
         State {
-            image,
+            selected: (!templates.is_empty()).then_some(0),
+            templates,
+            palette,
+            dither,
+            placer: None,
+            placer_revision: 0,
+            diff: None,
+            show_diff: true,
+            show_grid: true,
+            show_layers: false,
+            show_templates: false,
+            show_export: false,
+            show_help: false,
+            show_goto: false,
+            show_queue: false,
+            history: vec![],
+            shape_origin: None,
+            shape: vec![],
+            fill_rectangle: false,
+            export: ExportConfig::default(),
             focus: false,
+            typing: false,
             color: [1.0; 3],
             cooldown: 0.0,
+            revoked: false,
             selected_tool: ToolType::Mover,
+            config: Config::default(),
+            keymap: Keymap::default(),
             camera_state: CameraState::default(),
             menu_state: MenuState::default(),
         }
     }
+
+    pub fn add_template<P: AsRef<Path>>(&mut self, path: P) {
+        let mut template = match Template::new(path.as_ref()) {
+            Ok(template) => template,
+            Err(e) => {
+                println!("couldn't import {}: {e}", path.as_ref().display());
+                return;
+            }
+        };
+
+        if let Some(palette) = &self.palette {
+            if let Err(e) = template.set_palette(Some(palette), self.dither) {
+                println!("couldn't quantize {}: {e}", path.as_ref().display());
+            }
+        }
+
+        self.templates.add(template);
+        self.selected = Some(self.templates.len() - 1);
+        self.save_templates();
+    }
+
+    /// Replaces the placer and returns the previous one
+    pub fn set_placer(&mut self, placer: Option<Placer>) -> Option<Placer> {
+        self.placer_revision += 1;
+        std::mem::replace(&mut self.placer, placer)
+    }
+
+    pub fn remove_template(&mut self, id: usize) {
+        self.templates.remove(id);
+        self.selected = self.selected
+            .filter(|selected| *selected != id)
+            .map(|selected| if selected > id { selected - 1 } else { selected });
+        self.save_templates();
+    }
+
+    pub fn selected_template(&self) -> Option<&Template> {
+        self.templates.get(self.selected?)
+    }
+
+    pub fn selected_template_mut(&mut self) -> Option<&mut Template> {
+        self.templates.get_mut(self.selected?)
+    }
+
+    pub fn save_templates(&self) {
+        let Some(path) = templates_path() else {
+            return
+        };
+
+        if let Err(e) = self.templates.save(path) {
+            println!("couldn't save templates: {e}");
+        }
+    }
+}
+
+/// Loads the templates saved by earlier sessions
+pub fn load_templates() -> Templates {
+    templates_path()
+        .and_then(|path| Templates::load(path).ok())
+        .unwrap_or_default()
+}
+
+fn templates_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("pixels-client").join("templates.json"))
+}
+
+/// Loads the pixels left queued by earlier sessions
+pub fn load_queue() -> Queue {
+    queue_path()
+        .and_then(|path| Queue::load(path).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_queue(queue: &Queue) {
+    let Some(path) = queue_path() else {
+        return
+    };
+
+    if let Err(e) = queue.save(path) {
+        println!("couldn't save the queue: {e}");
+    }
+}
+
+fn queue_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("pixels-client").join("queue.json"))
+}
+
+/// Remembers when the cooldown ends so every frontend can report it
+pub fn save_cooldown(cooldown: f32) {
+    let Some(path) = cooldown_path() else {
+        return
+    };
+
+    let ends = now_secs() + cooldown as f64;
+    let result = match path.parent() {
+        Some(parent) => fs::create_dir_all(parent),
+        None => Ok(()),
+    };
+
+    if let Err(e) = result.and_then(|_| fs::write(&path, ends.to_string())) {
+        println!("couldn't save cooldown: {e}");
+    }
+}
+
+pub fn load_cooldown() -> f32 {
+    cooldown_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|ends| ends.trim().parse::<f64>().ok())
+        .map_or(0.0, |ends| (ends - now_secs()).max(0.0) as f32)
+}
+
+fn cooldown_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("pixels-client").join("cooldown"))
+}
+
+fn now_secs() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+}
+
+impl CameraState {
+    /// Places the camera without easing
+    pub fn jump_to(&mut self, position: Vec2) {
+        self.position = position;
+        self.target_position = position;
+    }
+
+    pub fn look_at(&mut self, position: Vec2) {
+        self.target_position = position;
+    }
+
+    /// Shifts the camera and its target alike so an ongoing zoom keeps its anchor
+    pub fn pan(&mut self, offset: Vec2) {
+        self.position += offset;
+        self.target_position += offset;
+    }
+
+    /// Zooms while keeping the world point under the screen position in place
+    pub fn zoom_at(&mut self, zoom: f32, screen: Vec2, config: &CameraConfig) {
+        let zoom = zoom.clamp(config.min_zoom, config.max_zoom);
+        let offset = screen - vec2(screen_width(), screen_height()) / 2.0;
+        let anchor = self.target_position + offset / self.target_zoom;
+
+        self.target_zoom = zoom;
+        self.target_position = anchor - offset / zoom;
+    }
+
+    /// Zooms so the whole area fits on screen and centers it
+    pub fn fit(&mut self, size: Vec2, config: &CameraConfig) {
+        let zoom = (vec2(screen_width(), screen_height()) / size).min_element() * 0.95;
+        self.target_zoom = zoom.clamp(config.min_zoom, config.max_zoom);
+        self.target_position = size / 2.0;
+    }
+
+    /// Moves towards the targets by `amount` between 0 and 1, zoom is eased
+    /// logarithmically so zooming in and out feel the same
+    pub fn ease(&mut self, amount: f32) {
+        let zoom = self.zoom.ln();
+        self.zoom = (zoom + (self.target_zoom.ln() - zoom) * amount).exp();
+        self.position = self.position.lerp(self.target_position, amount);
+    }
 }
 
 impl Default for CameraState {
     fn default() -> Self {
         CameraState {
             zoom: 3.0,
+            target_zoom: 3.0,
             instance: Camera2D::default(),
             position: vec2(0.0, 0.0),
+            target_position: vec2(0.0, 0.0),
             move_origin: vec2(0.0, 0.0),
         }
     }
@@ -68,6 +313,8 @@ impl Default for MenuState {
     fn default() -> Self {
         MenuState {
             area: Rect::NOTHING,
+            layer_name: String::new(),
+            goto: (0, 0),
             move_icon: RetainedImage::from_image_bytes(
                 "move_icon",
                 include_bytes!("../../assets/tool-move.png"),
@@ -84,6 +331,18 @@ impl Default for MenuState {
                 "picker_icon",
                 include_bytes!("../../assets/tool-picker.png"),
             ).unwrap(),
+            line_icon: RetainedImage::from_image_bytes(
+                "line_icon",
+                include_bytes!("../../assets/tool-line.png"),
+            ).unwrap(),
+            rectangle_icon: RetainedImage::from_image_bytes(
+                "rectangle_icon",
+                include_bytes!("../../assets/tool-rectangle.png"),
+            ).unwrap(),
+            fill_icon: RetainedImage::from_image_bytes(
+                "fill_icon",
+                include_bytes!("../../assets/tool-fill.png"),
+            ).unwrap(),
         }
     }
 }
@@ -0,0 +1,89 @@
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{
+    Arc,
+    Mutex
+};
+use std::thread;
+
+use clap::Parser;
+use tiny_http::Server;
+
+use state::State;
+
+mod routes;
+mod state;
+
+/// Local stand-in for the pixels event api
+#[derive(Parser)]
+pub struct Args {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    pub address: String,
+
+    /// Canvas width in pixels
+    #[arg(long, default_value_t = 256)]
+    pub width: u32,
+
+    /// Canvas height in pixels
+    #[arg(long, default_value_t = 256)]
+    pub height: u32,
+
+    /// Refresh tokens accepted by the authenticate endpoint
+    #[arg(long = "token", default_value = "dev")]
+    pub tokens: Vec<String>,
+
+    /// Pixel placements allowed per rate limit window
+    #[arg(long, default_value_t = 1)]
+    pub requests: u32,
+
+    /// Length of the rate limit window in seconds
+    #[arg(long, default_value_t = 5.0)]
+    pub reset: f32,
+
+    /// Lifetime of issued access tokens in seconds
+    #[arg(long, default_value_t = 3600)]
+    pub access_ttl: u64,
+}
+
+impl Default for Args {
+    /// The same settings as running the server without flags
+    fn default() -> Self {
+        Self {
+            address: String::from("127.0.0.1:8080"),
+            width: 256,
+            height: 256,
+            tokens: vec![String::from("dev")],
+            requests: 1,
+            reset: 5.0,
+            access_ttl: 3600,
+        }
+    }
+}
+
+/// Binds the address, port 0 picks a free one
+pub fn bind(address: &str) -> io::Result<Server> {
+    Server::http(address).map_err(io::Error::other)
+}
+
+/// Answers requests until the server is dropped, every request gets its own thread
+pub fn serve(server: Server, args: &Args) {
+    let state = Arc::new(Mutex::new(State::new(args)));
+
+    for request in server.incoming_requests() {
+        let state = state.clone();
+        thread::spawn(move || routes::handle(request, state));
+    }
+}
+
+/// Serves on a background thread and returns the bound address
+pub fn spawn(args: Args) -> io::Result<SocketAddr> {
+    let server = bind(&args.address)?;
+    let address = server
+        .server_addr()
+        .to_ip()
+        .ok_or_else(|| io::Error::other("not listening on an ip address"))?;
+
+    thread::spawn(move || serve(server, &args));
+    Ok(address)
+}
@@ -0,0 +1,11 @@
+use clap::Parser;
+
+use pixels_server::Args;
+
+fn main() {
+    let args = Args::parse();
+    let server = pixels_server::bind(&args.address).expect("couldn't start server");
+
+    println!("listening on http://{}", args.address);
+    pixels_server::serve(server, &args);
+}
@@ -0,0 +1,199 @@
+use std::io::Write;
+use std::sync::{
+    Arc,
+    Mutex
+};
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::Duration;
+
+use serde_json::{
+    json,
+    Value
+};
+use tiny_http::{
+    Header,
+    Method,
+    Request,
+    Response,
+    ResponseBox,
+};
+
+use super::state::{
+    AuthError,
+    PlaceError,
+    State,
+};
+
+const KEEPALIVE: Duration = Duration::from_secs(15);
+
+pub fn handle(mut request: Request, state: Arc<Mutex<State>>) {
+    let path = request.url().trim_matches('/').to_string();
+    let method = request.method().clone();
+
+    if method == Method::Get && path == "canvas/stream" {
+        return stream(request, state)
+    }
+
+    let mut body = String::new();
+    let response = match request.as_reader().read_to_string(&mut body) {
+        Ok(_) => {
+            let mut state = state.lock().unwrap();
+            route(&method, &path, &request, &body, &mut state)
+        }
+        Err(_) => message(400, "couldn't read request body"),
+    };
+
+    println!("{} /{} -> {}", method, path, response.status_code().0);
+    if let Err(e) = request.respond(response) {
+        println!("couldn't send response: {e}");
+    }
+}
+
+fn stream(request: Request, state: Arc<Mutex<State>>) {
+    let subscription = {
+        let mut state = state.lock().unwrap();
+        authorize(&request, &state).map(|_| state.subscribe())
+    };
+
+    let receiver = match subscription {
+        Ok(receiver) => receiver,
+        Err(response) => {
+            println!("GET /canvas/stream -> {}", response.status_code().0);
+            let _ = request.respond(response);
+            return
+        }
+    };
+
+    println!("GET /canvas/stream -> 200");
+    let mut writer = request.into_writer();
+    let mut result = writer.write_all(concat!(
+        "HTTP/1.1 200 OK\r\n",
+        "Content-Type: text/event-stream\r\n",
+        "Cache-Control: no-cache\r\n",
+        "Connection: close\r\n\r\n",
+    ).as_bytes()).and_then(|_| writer.flush());
+
+    while result.is_ok() {
+        let event = match receiver.recv_timeout(KEEPALIVE) {
+            Ok(event) => event,
+            Err(RecvTimeoutError::Timeout) => String::from(": keepalive\n\n"),
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        result = writer.write_all(event.as_bytes()).and_then(|_| writer.flush());
+    }
+}
+
+fn route(method: &Method, path: &str, request: &Request, body: &str, state: &mut State) -> ResponseBox {
+    match (method, path) {
+        (Method::Post, "authenticate") => authenticate(body, state),
+        (Method::Get, "canvas/size") => canvas_size(state),
+        (Method::Get, "canvas/pixels") => match authorize(request, state) {
+            Ok(_) => canvas_pixels(state),
+            Err(response) => response,
+        },
+        (Method::Put, "canvas/pixel") => match authorize(request, state) {
+            Ok(token) => canvas_set_pixel(&token, body, state),
+            Err(response) => response,
+        },
+        _ => message(404, "not found"),
+    }
+}
+
+fn authenticate(body: &str, state: &mut State) -> ResponseBox {
+    let refresh = serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|body| body["refresh_token"].as_str().map(String::from));
+
+    match refresh.and_then(|refresh| state.authenticate(&refresh)) {
+        Some(token) => json(200, json!({
+            "access_token": token,
+        })),
+        None => message(401, "invalid refresh token"),
+    }
+}
+
+fn canvas_size(state: &State) -> ResponseBox {
+    let (width, height) = state.size();
+    json(200, json!({
+        "width": width,
+        "height": height,
+    }))
+}
+
+fn canvas_pixels(state: &State) -> ResponseBox {
+    Response::from_data(state.pixels())
+        .with_header(header("Content-Type", "application/octet-stream"))
+        .boxed()
+}
+
+fn canvas_set_pixel(token: &str, body: &str, state: &mut State) -> ResponseBox {
+    let Some((x, y, rgb)) = parse_pixel(body) else {
+        return message(400, "expected x, y and rgb fields");
+    };
+
+    match state.set_pixel(token, x, y, rgb) {
+        Ok((remaining, reset)) => with_limits(
+            message(200, "pixel placed"), remaining, reset
+        ),
+        Err(PlaceError::RateLimited(reset)) => with_limits(
+            message(429, "too many requests"), 0, reset
+        ),
+        Err(PlaceError::OutOfBounds) => message(400, "pixel is out of bounds"),
+    }
+}
+
+fn parse_pixel(body: &str) -> Option<(u32, u32, [u8; 3])> {
+    let body: Value = serde_json::from_str(body).ok()?;
+    let hex = body["rgb"].as_str()?;
+
+    if hex.len() != 6 {
+        return None
+    }
+
+    let mut rgb = [0; 3];
+    for (i, channel) in rgb.iter_mut().enumerate() {
+        *channel = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+
+    Some((
+        body["x"].as_u64()? as u32,
+        body["y"].as_u64()? as u32,
+        rgb,
+    ))
+}
+
+fn with_limits(response: ResponseBox, remaining: u32, reset: f32) -> ResponseBox {
+    response
+        .with_header(header("requests-remaining", &remaining.to_string()))
+        .with_header(header("requests-reset", &reset.to_string()))
+}
+
+fn authorize(request: &Request, state: &State) -> Result<String, ResponseBox> {
+    let header = request.headers()
+        .iter()
+        .find(|header| header.field.equiv("Authorization"))
+        .map(|header| header.value.as_str());
+
+    state.authorize(header).map_err(|e| message(401, match e {
+        AuthError::Missing => "missing access token",
+        AuthError::Invalid => "invalid access token",
+        AuthError::Expired => "access token expired",
+    }))
+}
+
+fn message(status: u16, detail: &str) -> ResponseBox {
+    json(status, json!({
+        "detail": detail,
+    }))
+}
+
+fn json(status: u16, body: Value) -> ResponseBox {
+    Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(header("Content-Type", "application/json"))
+        .boxed()
+}
+
+fn header(field: &str, value: &str) -> Header {
+    Header::from_bytes(field.as_bytes(), value.as_bytes()).unwrap()
+}
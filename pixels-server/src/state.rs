@@ -0,0 +1,153 @@
+use std::collections::{
+    HashMap,
+    HashSet
+};
+use std::sync::mpsc::{
+    self,
+    Receiver,
+    Sender
+};
+use std::time::{
+    Duration,
+    Instant,
+    SystemTime,
+    UNIX_EPOCH
+};
+
+use serde_json::json;
+
+use super::Args;
+
+pub struct State {
+    size: (u32, u32),
+    pixels: Vec<u8>,
+    limits: Limits,
+    refresh_tokens: HashSet<String>,
+    sessions: HashMap<String, Session>,
+    subscribers: Vec<Sender<String>>,
+    counter: u64,
+}
+
+pub struct Limits {
+    pub requests: u32,
+    pub reset: Duration,
+    pub access_ttl: Duration,
+}
+
+struct Session {
+    expires: Instant,
+    remaining: u32,
+    reset: Instant,
+}
+
+pub enum AuthError {
+    Missing,
+    Invalid,
+    Expired,
+}
+
+pub enum PlaceError {
+    OutOfBounds,
+    RateLimited(f32),
+}
+
+impl State {
+    pub fn new(args: &Args) -> Self {
+        Self {
+            size: (args.width, args.height),
+            pixels: vec![255; (args.width * args.height * 3) as usize],
+            limits: Limits {
+                requests: args.requests,
+                reset: Duration::from_secs_f32(args.reset),
+                access_ttl: Duration::from_secs(args.access_ttl),
+            },
+            refresh_tokens: args.tokens.iter().cloned().collect(),
+            sessions: HashMap::new(),
+            subscribers: vec![],
+            counter: 0,
+        }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn subscribe(&mut self) -> Receiver<String> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    pub fn authenticate(&mut self, refresh: &str) -> Option<String> {
+        if !self.refresh_tokens.contains(refresh) {
+            return None
+        }
+
+        let now = Instant::now();
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+
+        self.counter += 1;
+        let token = format!("{:x}{:08x}", nanos, self.counter);
+
+        self.sessions.insert(token.clone(), Session {
+            expires: now + self.limits.access_ttl,
+            remaining: self.limits.requests,
+            reset: now + self.limits.reset,
+        });
+        Some(token)
+    }
+
+    pub fn authorize(&self, header: Option<&str>) -> Result<String, AuthError> {
+        let token = header
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or(AuthError::Missing)?;
+        let session = self.sessions
+            .get(token)
+            .ok_or(AuthError::Invalid)?;
+
+        if Instant::now() >= session.expires {
+            return Err(AuthError::Expired)
+        }
+        Ok(String::from(token))
+    }
+
+    pub fn set_pixel(&mut self, token: &str, x: u32, y: u32, rgb: [u8; 3]) -> Result<(u32, f32), PlaceError> {
+        let session = self.sessions.get_mut(token).unwrap();
+        let now = Instant::now();
+
+        if now >= session.reset {
+            session.remaining = self.limits.requests;
+            session.reset = now + self.limits.reset;
+        }
+        let reset = (session.reset - now).as_secs_f32();
+
+        if session.remaining == 0 {
+            return Err(PlaceError::RateLimited(reset))
+        }
+        if x >= self.size.0 || y >= self.size.1 {
+            return Err(PlaceError::OutOfBounds)
+        }
+
+        session.remaining -= 1;
+        let index = ((y * self.size.0 + x) * 3) as usize;
+        self.pixels[index..index + 3].copy_from_slice(&rgb);
+
+        let event = format!("event: pixel\ndata: {}\n\n", json!({
+            "x": x,
+            "y": y,
+            "rgb": format!("{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2]),
+        }));
+        self.subscribers.retain(|subscriber| {
+            subscriber.send(event.clone()).is_ok()
+        });
+
+        Ok((session.remaining, reset))
+    }
+}
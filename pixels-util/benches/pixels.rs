@@ -0,0 +1,91 @@
+use criterion::{
+    black_box,
+    criterion_group,
+    criterion_main,
+    Criterion
+};
+
+use pixels_util::prelude::*;
+
+const SIZE: (u32, u32) = (1024, 1024);
+
+/// The nested vector storage `Pixels` used before the flat buffer, kept to compare against
+mod nested {
+    use pixels_util::prelude::*;
+
+    #[derive(Clone)]
+    pub struct Pixels {
+        size: (u32, u32),
+        data: Vec<Vec<Color>>,
+    }
+
+    impl Pixels {
+        pub fn new(size: (u32, u32)) -> Self {
+            Self {
+                size,
+                data: vec![vec![Color::default(); size.0 as usize]; size.1 as usize],
+            }
+        }
+
+        pub fn get(&self, x: u32, y: u32) -> Option<Color> {
+            Some(*self.data.get(y as usize)?.get(x as usize)?)
+        }
+
+        pub fn set(&mut self, x: u32, y: u32, color: Color) {
+            if let Some(item) = self.data.get_mut(y as usize).and_then(|row| row.get_mut(x as usize)) {
+                *item = color;
+            }
+        }
+
+        pub fn overlay(&self, other: &Pixels, alpha: f32) -> Pixels {
+            let mut result = Pixels::new(self.size);
+            for ((x, y), src) in self.iter() {
+                let dst = other.get(x, y).unwrap();
+                result.set(x, y, src.merge_alpha(dst, alpha));
+            }
+            result
+        }
+
+        /// Cloned the whole storage up front like the old iterator did
+        pub fn iter(&self) -> impl Iterator<Item = ((u32, u32), Color)> {
+            let pixels = self.clone();
+            let (width, height) = pixels.size;
+            (0..height).flat_map(move |y| (0..width).map(move |x| (x, y)))
+                .map(move |(x, y)| ((x, y), pixels.get(x, y).unwrap_or_default()))
+        }
+    }
+}
+
+fn filled<T>(mut set: impl FnMut(&mut T, u32, u32, Color), mut pixels: T) -> T {
+    for y in 0..SIZE.1 {
+        for x in 0..SIZE.0 {
+            set(&mut pixels, x, y, Color::from_rgba((x % 256) as u8, (y % 256) as u8, 128, 200));
+        }
+    }
+    pixels
+}
+
+fn iter(c: &mut Criterion) {
+    let nested = filled(|p: &mut nested::Pixels, x, y, color| p.set(x, y, color), nested::Pixels::new(SIZE));
+    let flat = filled(|p: &mut Pixels, x, y, color| p.set(x, y, color), Pixels::new(SIZE));
+
+    let mut group = c.benchmark_group("iter");
+    group.sample_size(10);
+    group.bench_function("nested", |b| b.iter(|| black_box(&nested).iter().map(|(_, color)| color.r).sum::<f32>()));
+    group.bench_function("flat", |b| b.iter(|| black_box(&flat).iter().map(|(_, color)| color.r).sum::<f32>()));
+    group.finish();
+}
+
+fn blend(c: &mut Criterion) {
+    let nested = filled(|p: &mut nested::Pixels, x, y, color| p.set(x, y, color), nested::Pixels::new(SIZE));
+    let flat = filled(|p: &mut Pixels, x, y, color| p.set(x, y, color), Pixels::new(SIZE));
+
+    let mut group = c.benchmark_group("blend");
+    group.sample_size(10);
+    group.bench_function("nested", |b| b.iter(|| black_box(&nested).overlay(&nested, 0.5)));
+    group.bench_function("flat", |b| b.iter(|| black_box(&flat).blend(&flat, 0.5, BlendMode::Normal)));
+    group.finish();
+}
+
+criterion_group!(benches, iter, blend);
+criterion_main!(benches);
@@ -1,17 +1,30 @@
 use image::ColorType;
 
 use crate::{
-    from, into,
+    from,
     normalize_color,
     denormalize_color
 };
 
+/// Onion skinned layers show as a faded ghost at this fraction of their opacity
+const ONION_ALPHA: f32 = 0.35;
+
 #[derive(Copy, Clone)]
 pub enum ColorMode {
     RGBA,
     RGB
 }
 
+/// How a layer is combined with the layers below it
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    Multiply,
+    Difference,
+    OnionSkin,
+}
+
 #[derive(Copy, Clone, Default)]
 pub struct Color {
     pub r: f32,
@@ -59,6 +72,19 @@ impl Color {
         }
     }
 
+    pub fn from_hex(hex: &str, mode: ColorMode) -> Option<Color> {
+        let hex = hex.trim_start_matches('#');
+        if hex.len() != mode.size() * 2 {
+            return None
+        }
+
+        let mut buffer = [255; 4];
+        for (i, channel) in buffer.iter_mut().take(mode.size()).enumerate() {
+            *channel = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+        }
+        Some(Color::from_slice(&buffer, mode))
+    }
+
     pub fn to_rgba(&self) -> (u8, u8, u8, u8) {
         (
             denormalize_color!(self.r),
@@ -100,6 +126,35 @@ impl Color {
             a: self.a * inv_alpha + other.a * alpha,
         }
     }
+
+    /// Composites other over self, other is weighted by its own alpha times the given alpha
+    pub fn blend(&self, other: Self, alpha: f32, mode: BlendMode) -> Color {
+        let (blended, weight) = match mode {
+            BlendMode::Normal => ([other.r, other.g, other.b], 1.0),
+            BlendMode::Multiply => ([self.r * other.r, self.g * other.g, self.b * other.b], 1.0),
+            BlendMode::Difference => ([
+                (self.r - other.r).abs(),
+                (self.g - other.g).abs(),
+                (self.b - other.b).abs(),
+            ], 1.0),
+            BlendMode::OnionSkin => {
+                let luma = 0.2126 * other.r + 0.7152 * other.g + 0.0722 * other.b;
+                ([
+                    (other.r + luma) / 2.0,
+                    (other.g + luma) / 2.0,
+                    (other.b + luma) / 2.0,
+                ], ONION_ALPHA)
+            }
+        };
+
+        let t = (other.a * alpha * weight).clamp(0.0, 1.0);
+        Self {
+            r: self.r + (blended[0] - self.r) * t,
+            g: self.g + (blended[1] - self.g) * t,
+            b: self.b + (blended[2] - self.b) * t,
+            a: self.a + (1.0 - self.a) * t,
+        }
+    }
 }
 
 from!([u8; 3], Color, |value: [u8; 3]| {
@@ -136,6 +191,24 @@ from!(Color, [f32; 4], |value: Color| {
     [value.r, value.g, value.b, value.a]
 });
 
+impl BlendMode {
+    pub const ALL: [BlendMode; 4] = [
+        BlendMode::Normal,
+        BlendMode::Multiply,
+        BlendMode::Difference,
+        BlendMode::OnionSkin,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BlendMode::Normal => "normal",
+            BlendMode::Multiply => "multiply",
+            BlendMode::Difference => "difference",
+            BlendMode::OnionSkin => "onion-skin",
+        }
+    }
+}
+
 impl ColorMode {
     pub fn from(format: ColorType) -> Option<Self> {
         match format {
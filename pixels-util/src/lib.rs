@@ -1,10 +1,14 @@
 pub mod cooldown;
+pub mod palette;
 pub mod pixels;
+pub mod region;
 pub mod color;
+pub mod space;
 
 pub mod prelude {
     pub use crate::{
         color::{
+            BlendMode,
             Color,
             ColorMode
         },
@@ -12,6 +16,11 @@ pub mod prelude {
             Pixels,
             PixelsIterator
         },
+        palette::{
+            Dither,
+            Palette
+        },
+        region::Region,
         cooldown::Cooldown,
     };
 }
@@ -0,0 +1,248 @@
+use std::fs;
+use std::io::{
+    self,
+    ErrorKind
+};
+use std::path::Path;
+use std::str::FromStr;
+
+use super::prelude::*;
+
+const PRESETS: [(&str, &[&str]); 4] = [
+    ("rplace", &[
+        "6d001a", "be0039", "ff4500", "ffa800", "ffd635", "fff8b8", "00a368", "00cc78",
+        "7eed56", "00756f", "009eaa", "00ccc0", "2450a4", "3690ea", "51e9f4", "493ac1",
+        "6a5cff", "94b3ff", "811e9f", "b44ac0", "e4abff", "de107f", "ff3881", "ff99aa",
+        "6d482f", "9c6926", "ffb470", "000000", "515252", "898d90", "d4d7d9", "ffffff",
+    ]),
+    ("rplace-2017", &[
+        "ffffff", "e4e4e4", "888888", "222222", "ffa7d1", "e50000", "e59500", "a06a42",
+        "e5d900", "94e044", "02be01", "00d3dd", "0083c7", "0000ea", "cf6ee4", "820080",
+    ]),
+    ("pico-8", &[
+        "000000", "1d2b53", "7e2553", "008751", "ab5236", "5f574f", "c2c3c7", "fff1e8",
+        "ff004d", "ffa300", "ffec27", "00e436", "29adff", "83769c", "ff77a8", "ffccaa",
+    ]),
+    ("gameboy", &[
+        "0f380f", "306230", "8bac0f", "9bbc0f",
+    ]),
+];
+
+const BAYER: [[f32; 4]; 4] = [
+    [0.0, 8.0, 2.0, 10.0],
+    [12.0, 4.0, 14.0, 6.0],
+    [3.0, 11.0, 1.0, 9.0],
+    [15.0, 7.0, 13.0, 5.0],
+];
+
+#[derive(Clone)]
+pub struct Palette {
+    colors: Vec<Color>,
+    oklab: Vec<(f32, f32, f32)>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Dither {
+    None,
+    FloydSteinberg,
+    Ordered,
+}
+
+impl Palette {
+    pub fn new(colors: Vec<Color>) -> Self {
+        Self {
+            oklab: colors.iter().map(Color::to_oklab).collect(),
+            colors,
+        }
+    }
+
+    pub fn presets() -> impl Iterator<Item = &'static str> {
+        PRESETS.iter().map(|(name, _)| *name)
+    }
+
+    pub fn preset(name: &str) -> Option<Self> {
+        let (_, colors) = PRESETS.iter().find(|(preset, _)| *preset == name)?;
+        Some(Self::new(colors
+            .iter()
+            .filter_map(|hex| Color::from_hex(hex, ColorMode::RGB))
+            .collect()
+        ))
+    }
+
+    /// Parses a GIMP palette, one `r g b name` entry per line
+    pub fn from_gpl(text: &str) -> Option<Self> {
+        let mut lines = text.lines();
+        if lines.next()?.trim() != "GIMP Palette" {
+            return None
+        }
+
+        let colors: Vec<Color> = lines
+            .filter_map(|line| {
+                let mut channels = line.split_whitespace().map(u8::from_str);
+                Some(Color::from_rgb(
+                    channels.next()?.ok()?,
+                    channels.next()?.ok()?,
+                    channels.next()?.ok()?,
+                ))
+            })
+            .collect();
+
+        (!colors.is_empty()).then(|| Self::new(colors))
+    }
+
+    /// Parses one `rrggbb` color per line, lines starting with `;` or `//` are skipped
+    pub fn from_hex_list(text: &str) -> Option<Self> {
+        let colors = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with(';') && !line.starts_with("//"))
+            .map(|line| Color::from_hex(line, ColorMode::RGB))
+            .collect::<Option<Vec<Color>>>()?;
+
+        (!colors.is_empty()).then(|| Self::new(colors))
+    }
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let text = fs::read_to_string(path.as_ref())?;
+        let palette = match path.as_ref().extension().and_then(|ext| ext.to_str()) {
+            Some("gpl") => Self::from_gpl(&text),
+            _ => Self::from_hex_list(&text),
+        };
+        palette.ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "invalid palette file"))
+    }
+
+    /// Resolves a preset name or falls back to loading a palette file
+    pub fn load(name: &str) -> io::Result<Self> {
+        match Self::preset(name) {
+            Some(palette) => Ok(palette),
+            None => Self::from_path(name),
+        }
+    }
+
+    pub fn colors(&self) -> &[Color] {
+        &self.colors
+    }
+
+    /// Picks the palette color with the smallest oklab distance
+    pub fn nearest(&self, color: Color) -> Color {
+        let (l, a, b) = color.to_oklab();
+        let distance = |other: &(f32, f32, f32)| {
+            (other.0 - l).powi(2) + (other.1 - a).powi(2) + (other.2 - b).powi(2)
+        };
+
+        let nearest = self.oklab
+            .iter()
+            .zip(self.colors.iter())
+            .min_by(|x, y| distance(x.0).total_cmp(&distance(y.0)))
+            .map(|(_, color)| *color)
+            .unwrap_or(color);
+        Color::new(nearest.r, nearest.g, nearest.b, color.a)
+    }
+
+    pub fn quantize(&self, pixels: &Pixels, dither: Dither) -> Pixels {
+        match dither {
+            Dither::None => self.quantize_plain(pixels),
+            Dither::FloydSteinberg => self.quantize_floyd_steinberg(pixels),
+            Dither::Ordered => self.quantize_ordered(pixels),
+        }
+    }
+
+    fn quantize_plain(&self, pixels: &Pixels) -> Pixels {
+        let mut result = Pixels::new(pixels.size());
+        for ((x, y), color) in pixels.iter() {
+            result.set(x, y, self.nearest(color));
+        }
+        result
+    }
+
+    fn quantize_floyd_steinberg(&self, pixels: &Pixels) -> Pixels {
+        let (width, height) = pixels.size();
+        let mut errors = vec![[0.0f32; 3]; (width * height) as usize];
+        let mut result = Pixels::new(pixels.size());
+
+        for ((x, y), color) in pixels.iter() {
+            let error = errors[(y * width + x) as usize];
+            let old = Color::new(
+                (color.r + error[0]).clamp(0.0, 1.0),
+                (color.g + error[1]).clamp(0.0, 1.0),
+                (color.b + error[2]).clamp(0.0, 1.0),
+                color.a,
+            );
+            let new = self.nearest(old);
+            result.set(x, y, new);
+
+            if color.a == 0.0 {
+                continue
+            }
+
+            let error = [old.r - new.r, old.g - new.g, old.b - new.b];
+            let neighbours = [
+                (1, 0, 7.0 / 16.0),
+                (-1, 1, 3.0 / 16.0),
+                (0, 1, 5.0 / 16.0),
+                (1, 1, 1.0 / 16.0),
+            ];
+
+            for (dx, dy, weight) in neighbours {
+                let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+                if nx < 0 || nx >= width as i64 || ny >= height as i64 {
+                    continue
+                }
+
+                let target = &mut errors[(ny * width as i64 + nx) as usize];
+                for (channel, value) in target.iter_mut().zip(error) {
+                    *channel += value * weight;
+                }
+            }
+        }
+
+        result
+    }
+
+    fn quantize_ordered(&self, pixels: &Pixels) -> Pixels {
+        let spread = 1.0 / (self.colors.len().max(2) as f32).cbrt();
+        let mut result = Pixels::new(pixels.size());
+
+        for ((x, y), color) in pixels.iter() {
+            let offset = (BAYER[y as usize % 4][x as usize % 4] + 0.5) / 16.0 - 0.5;
+            let shifted = Color::new(
+                (color.r + offset * spread).clamp(0.0, 1.0),
+                (color.g + offset * spread).clamp(0.0, 1.0),
+                (color.b + offset * spread).clamp(0.0, 1.0),
+                color.a,
+            );
+            result.set(x, y, self.nearest(shifted));
+        }
+
+        result
+    }
+}
+
+impl Dither {
+    pub const ALL: [Dither; 3] = [
+        Dither::None,
+        Dither::FloydSteinberg,
+        Dither::Ordered,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dither::None => "none",
+            Dither::FloydSteinberg => "floyd-steinberg",
+            Dither::Ordered => "ordered",
+        }
+    }
+}
+
+impl FromStr for Dither {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "none" => Ok(Self::None),
+            "floyd-steinberg" => Ok(Self::FloydSteinberg),
+            "ordered" => Ok(Self::Ordered),
+            _ => Err(format!("unknown dither {value}, expected none, floyd-steinberg or ordered")),
+        }
+    }
+}
@@ -1,49 +1,133 @@
 use std::path::Path;
+use std::io::Cursor;
 use image::io::Reader;
-use image::GenericImageView;
+use image::{
+    DynamicImage,
+    ImageResult,
+    RgbaImage
+};
 
 use super::prelude::*;
 
+const CHANNELS: usize = 4;
+pub const MAX_DIRTY: usize = 32;
+
+/// Pixel storage as one contiguous rgba8 buffer, row by row.
+/// Writes are tracked as dirty regions until they are taken.
 #[derive(Clone)]
 pub struct Pixels {
     size: (u32, u32),
-    data: Vec<Vec<Color>>,
+    data: Vec<u8>,
+    dirty: Vec<Region>,
+    extent: Option<Region>,
+    revision: u64,
 }
 
 impl Pixels {
     pub fn new(size: (u32, u32)) -> Self {
         Self {
             size,
-            data: vec![
-                vec![
-                    Color::default();
-                    size.0 as usize
-                ]; size.1 as usize
-            ],
+            data: vec![0; size.0 as usize * size.1 as usize * CHANNELS],
+            dirty: vec![],
+            extent: None,
+            revision: 0,
         }
     }
 
+    /// Takes over the buffer, panics when it doesn't hold exactly the size's pixels
     pub fn from_buffer(size: (u32, u32), buffer: Vec<u8>, mode: ColorMode) -> Self {
-        let mut instance = Self::new(size);
+        let length = size.0 as usize * size.1 as usize * CHANNELS;
+
+        let data: Vec<u8> = match mode {
+            ColorMode::RGBA => buffer,
+            ColorMode::RGB => buffer
+                .chunks_exact(3)
+                .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 255])
+                .collect(),
+        };
+        assert_eq!(data.len(), length, "buffer doesn't match the size");
 
-        for (i, pixel) in instance.data.iter_mut().flatten().enumerate() {
-            *pixel = Color::from_slice(&buffer[i*mode.size()..], mode);
+        Self {
+            size,
+            data,
+            dirty: vec![],
+            extent: Some(Region::from_size(size)),
+            revision: 0,
         }
+    }
 
-        instance
+    /// Decodes an image file, the format is guessed from its contents
+    pub fn from_path<P: AsRef<Path>>(path: P) -> ImageResult<Self> {
+        let image = Reader::open(path)?
+            .with_guessed_format()?
+            .decode()?;
+        Ok(Self::from_image(image))
     }
 
-    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
-        let image = Reader::open(path)
-            .unwrap().decode().unwrap();
+    /// Decodes an in-memory image, the format is guessed from its contents
+    pub fn from_bytes(bytes: &[u8]) -> ImageResult<Self> {
+        let image = Reader::new(Cursor::new(bytes))
+            .with_guessed_format()?
+            .decode()?;
+        Ok(Self::from_image(image))
+    }
 
+    /// Converts any color type, including grayscale, 16-bit and float images, to rgba8
+    pub fn from_image(image: DynamicImage) -> Self {
+        let image = image.into_rgba8();
         Self::from_buffer(
             image.dimensions(),
-            image.clone().into_bytes(),
-            ColorMode::from(image.color()).expect("unsupported format")
+            image.into_raw(),
+            ColorMode::RGBA,
         )
     }
 
+    pub fn to_image(&self) -> RgbaImage {
+        RgbaImage::from_raw(self.width(), self.height(), self.data.clone())
+            .expect("buffer matches the size")
+    }
+
+    /// Encodes the pixels to a file, the format follows the extension
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> ImageResult<()> {
+        self.to_image().save(path)
+    }
+
+    /// Copies the region out, clipped to the bounds
+    pub fn crop(&self, region: Region) -> Pixels {
+        let region = region
+            .intersection(&Region::from_size(self.size))
+            .unwrap_or_default();
+
+        Self::from_buffer(
+            (region.width, region.height),
+            self.region(region.x, region.y, region.width, region.height)
+                .flatten()
+                .copied()
+                .collect(),
+            ColorMode::RGBA,
+        )
+    }
+
+    /// Scales up by an integer factor, every pixel becomes a factor sized square
+    pub fn upscale(&self, factor: u32) -> Pixels {
+        let factor = factor.max(1);
+        let size = (self.width() * factor, self.height() * factor);
+        let mut data = Vec::with_capacity(size.0 as usize * size.1 as usize * CHANNELS);
+
+        for y in 0..self.height() {
+            let row: Vec<u8> = self.row(y)
+                .unwrap_or_default()
+                .chunks_exact(CHANNELS)
+                .flat_map(|pixel| pixel.repeat(factor as usize))
+                .collect();
+            for _ in 0..factor {
+                data.extend_from_slice(&row);
+            }
+        }
+
+        Self::from_buffer(size, data, ColorMode::RGBA)
+    }
+
     pub fn width(&self) -> u32 {
         self.size.0
     }
@@ -56,73 +140,323 @@ impl Pixels {
         self.size
     }
 
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+
+    pub fn row(&self, y: u32) -> Option<&[u8]> {
+        let stride = self.stride();
+        let start = y as usize * stride;
+        self.data.get(start..start + stride)
+    }
+
+    pub fn row_mut(&mut self, y: u32) -> Option<&mut [u8]> {
+        let stride = self.stride();
+        let start = y as usize * stride;
+        self.data.get_mut(start..start + stride)
+    }
+
+    /// Yields one slice per row of the region, clipped to the bounds
+    pub fn region(&self, x: u32, y: u32, width: u32, height: u32) -> impl Iterator<Item = &[u8]> {
+        let left = x.min(self.width()) as usize * CHANNELS;
+        let right = x.saturating_add(width).min(self.width()) as usize * CHANNELS;
+
+        (y..y.saturating_add(height).min(self.height()))
+            .filter_map(move |row| self.row(row))
+            .map(move |row| &row[left..right])
+    }
+
     pub fn get(&self, x: u32, y: u32) -> Option<Color> {
-        Some(*self.data
-            .get(y as usize)?
-            .get(x as usize)?)
+        let index = self.index(x, y)?;
+        Some(Color::from_slice(&self.data[index..index + CHANNELS], ColorMode::RGBA))
     }
 
+    /// Writes the pixel, setting the color it already has marks nothing
     pub fn set(&mut self, x: u32, y: u32, color: Color) {
-        if let Some(item) = self.data
-            .get_mut(y as usize)
-            .and_then(|list| {
-                list.get_mut(x as usize)
-            }) {
-            *item = color;
+        let Some(index) = self.index(x, y) else {
+            return
+        };
+
+        let color: [u8; 4] = color.into();
+        if self.data[index..index + CHANNELS] != color {
+            self.data[index..index + CHANNELS].copy_from_slice(&color);
+            self.mark(Region::new(x, y, 1, 1));
         }
     }
 
+    /// Clears the area written since the last clear
+    pub fn clear(&mut self) {
+        let Some(extent) = self.extent.take() else {
+            return
+        };
+
+        let left = extent.x as usize * CHANNELS;
+        let right = extent.right() as usize * CHANNELS;
+        for y in extent.y..extent.bottom() {
+            if let Some(row) = self.row_mut(y) {
+                row[left..right].fill(0);
+            }
+        }
+        self.mark(extent);
+        self.extent = None;
+    }
+
+    /// Copies the other pixels over, marking only the pixels that differ.
+    /// Returns the count of changed pixels.
+    pub fn replace(&mut self, other: &Pixels) -> usize {
+        if other.size != self.size {
+            let revision = self.revision + 1;
+            *self = other.clone();
+            self.dirty = vec![Region::from_size(self.size)];
+            self.revision = revision;
+            return (self.size.0 * self.size.1) as usize
+        }
+
+        let mut changed = 0;
+        for y in 0..self.height() {
+            let (Some(old), Some(new)) = (self.row(y), other.row(y)) else {
+                continue
+            };
+            if old == new {
+                continue
+            }
+
+            let pixels = old.chunks_exact(CHANNELS).zip(new.chunks_exact(CHANNELS));
+            let mut first = None;
+            let mut last = 0;
+            for (x, (a, b)) in pixels.enumerate() {
+                if a != b {
+                    first.get_or_insert(x);
+                    last = x;
+                    changed += 1;
+                }
+            }
+
+            if let Some(first) = first {
+                self.row_mut(y).unwrap().copy_from_slice(new);
+                self.mark(Region::new(first as u32, y, (last - first + 1) as u32, 1));
+            }
+        }
+
+        self.extent = Some(Region::from_size(self.size));
+        changed
+    }
+
+    /// Marks a region as changed, it's merged into a touching dirty region when possible
+    pub fn mark(&mut self, region: Region) {
+        let Some(region) = region.intersection(&Region::from_size(self.size)) else {
+            return
+        };
+
+        self.extent = Some(match self.extent {
+            Some(extent) => extent.union(&region),
+            None => region,
+        });
+
+        region.merge_into(&mut self.dirty, MAX_DIRTY);
+        self.revision += 1;
+    }
+
+    /// Counts the changes, it differs whenever the pixels may have changed
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn take_dirty(&mut self) -> Vec<Region> {
+        std::mem::take(&mut self.dirty)
+    }
+
     pub fn overlay(&self, other: &Pixels, alpha: f32) -> Pixels {
-        let mut result = Pixels::new(self.size);
+        let mut result = self.clone();
 
-        for ((x, y), src) in self.iter() {
-            let dst = other.get(x, y).unwrap();
-            result.set(x, y, src.merge_alpha(dst, alpha));
+        for ((x, y), dst) in other.iter() {
+            if let Some(src) = self.get(x, y) {
+                result.set(x, y, src.merge_alpha(dst, alpha));
+            }
         }
 
         result
     }
 
-    pub fn iter(&self) -> PixelsIterator {
-        PixelsIterator::new(self.clone())
+    pub fn blend(&self, other: &Pixels, alpha: f32, mode: BlendMode) -> Pixels {
+        let mut result = self.clone();
+
+        for ((x, y), dst) in other.iter() {
+            if let Some(src) = self.get(x, y) {
+                result.set(x, y, src.blend(dst, alpha, mode));
+            }
+        }
+
+        result
+    }
+
+    pub fn iter(&self) -> PixelsIterator<'_> {
+        PixelsIterator::new(self)
+    }
+
+    fn stride(&self) -> usize {
+        self.size.0 as usize * CHANNELS
+    }
+
+    fn index(&self, x: u32, y: u32) -> Option<usize> {
+        if x >= self.width() || y >= self.height() {
+            return None
+        }
+        Some(y as usize * self.stride() + x as usize * CHANNELS)
     }
 }
 
-pub struct PixelsIterator {
-    pixels: Pixels,
-    x: u32,
-    y: u32,
+pub struct PixelsIterator<'a> {
+    pixels: &'a Pixels,
+    index: usize,
 }
 
-impl PixelsIterator {
-    fn new(pixels: Pixels) -> Self {
+impl<'a> PixelsIterator<'a> {
+    fn new(pixels: &'a Pixels) -> Self {
         Self {
             pixels,
-            x: 0,
-            y: 0,
+            index: 0,
         }
     }
 }
 
-impl Iterator for PixelsIterator {
+impl Iterator for PixelsIterator<'_> {
     type Item = ((u32, u32), Color);
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.y >= self.pixels.height() {
-            return None;
+        let width = self.pixels.width() as usize;
+        if width == 0 {
+            return None
         }
 
-        let result = Some((
-            (self.x, self.y),
-            self.pixels.get(self.x, self.y).unwrap_or_default(),
-        ));
+        let pixel = self.pixels.data.get(self.index * CHANNELS..(self.index + 1) * CHANNELS)?;
+        let result = (
+            ((self.index % width) as u32, (self.index / width) as u32),
+            Color::from_slice(pixel, ColorMode::RGBA),
+        );
 
-        self.x += 1;
-        if self.x >= self.pixels.width() {
-            self.x = 0;
-            self.y += 1;
-        }
+        self.index += 1;
+        Some(result)
+    }
 
-        result
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.pixels.data.len() / CHANNELS - self.index;
+        (remaining, Some(remaining))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn red() -> Color {
+        Color::from_rgb(255, 0, 0)
+    }
+
+    fn rgba(pixels: &Pixels, x: u32, y: u32) -> (u8, u8, u8, u8) {
+        pixels.get(x, y).unwrap().to_rgba()
+    }
+
+    #[test]
+    fn set_with_the_same_color_marks_nothing() {
+        let mut pixels = Pixels::new((4, 3));
+        pixels.set(1, 2, red());
+        assert_eq!(pixels.take_dirty(), vec![Region::new(1, 2, 1, 1)]);
+
+        pixels.set(1, 2, red());
+        pixels.set(0, 0, Color::default());
+        assert!(pixels.take_dirty().is_empty());
+    }
+
+    #[test]
+    fn revision_changes_only_with_the_pixels() {
+        let mut pixels = Pixels::new((4, 3));
+        let revision = pixels.revision();
+
+        pixels.set(1, 1, red());
+        pixels.set(1, 1, red());
+        assert_eq!(pixels.revision(), revision + 1);
+
+        pixels.replace(&Pixels::new((2, 2)));
+        assert!(pixels.revision() > revision + 1);
+    }
+
+    #[test]
+    fn replace_marks_only_the_changed_span() {
+        let mut pixels = Pixels::new((4, 3));
+        let mut other = pixels.clone();
+        other.set(1, 1, red());
+        other.set(3, 1, red());
+
+        assert_eq!(pixels.replace(&other), 2);
+        assert_eq!(pixels.take_dirty(), vec![Region::new(1, 1, 3, 1)]);
+        assert_eq!(rgba(&pixels, 3, 1), (255, 0, 0, 255));
+        assert_eq!(rgba(&pixels, 2, 1), (0, 0, 0, 0));
+    }
+
+    #[test]
+    fn replace_with_identical_pixels_changes_nothing() {
+        let mut pixels = Pixels::new((4, 3));
+        pixels.set(2, 2, red());
+        pixels.take_dirty();
+
+        assert_eq!(pixels.replace(&pixels.clone()), 0);
+        assert!(pixels.take_dirty().is_empty());
+    }
+
+    #[test]
+    fn replace_with_another_size_takes_it_over() {
+        let mut pixels = Pixels::new((4, 3));
+        let other = Pixels::new((2, 2));
+
+        assert_eq!(pixels.replace(&other), 4);
+        assert_eq!(pixels.size(), (2, 2));
+        assert_eq!(pixels.take_dirty(), vec![Region::from_size((2, 2))]);
+    }
+
+    #[test]
+    fn clear_resets_the_written_extent() {
+        let mut pixels = Pixels::new((4, 3));
+        pixels.set(1, 1, red());
+        pixels.set(2, 2, red());
+        pixels.take_dirty();
+
+        pixels.clear();
+        assert_eq!(rgba(&pixels, 1, 1), (0, 0, 0, 0));
+        assert_eq!(rgba(&pixels, 2, 2), (0, 0, 0, 0));
+        assert_eq!(pixels.take_dirty(), vec![Region::new(1, 1, 2, 2)]);
+
+        pixels.clear();
+        assert!(pixels.take_dirty().is_empty());
+    }
+
+    #[test]
+    fn region_is_clipped_to_the_bounds() {
+        let mut pixels = Pixels::new((4, 3));
+        pixels.set(3, 2, red());
+
+        let rows: Vec<&[u8]> = pixels.region(2, 1, 5, 5).collect();
+        assert_eq!(rows.len(), 2);
+        assert!(rows.iter().all(|row| row.len() == 2 * CHANNELS));
+        assert_eq!(&rows[1][CHANNELS..], &[255, 0, 0, 255]);
+
+        assert_eq!(pixels.region(4, 3, 2, 2).flatten().count(), 0);
+    }
+
+    #[test]
+    fn crop_is_clipped_to_the_bounds() {
+        let mut pixels = Pixels::new((4, 3));
+        pixels.set(2, 1, red());
+
+        let cropped = pixels.crop(Region::new(2, 1, 10, 10));
+        assert_eq!(cropped.size(), (2, 2));
+        assert_eq!(rgba(&cropped, 0, 0), (255, 0, 0, 255));
+        assert_eq!(rgba(&cropped, 1, 1), (0, 0, 0, 0));
+
+        assert_eq!(pixels.crop(Region::new(8, 8, 2, 2)).size(), (0, 0));
     }
 }
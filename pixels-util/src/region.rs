@@ -0,0 +1,104 @@
+use std::str::FromStr;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Region {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn from_size(size: (u32, u32)) -> Self {
+        Self::new(0, 0, size.0, size.1)
+    }
+
+    pub fn right(&self) -> u32 {
+        self.x.saturating_add(self.width)
+    }
+
+    pub fn bottom(&self) -> u32 {
+        self.y.saturating_add(self.height)
+    }
+
+    pub fn area(&self) -> u64 {
+        self.width as u64 * self.height as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        x >= self.x && x < self.right() && y >= self.y && y < self.bottom()
+    }
+
+    /// Returns whether the regions overlap or share an edge
+    pub fn touches(&self, other: &Region) -> bool {
+        self.x <= other.right() && other.x <= self.right() &&
+            self.y <= other.bottom() && other.y <= self.bottom()
+    }
+
+    pub fn union(&self, other: &Region) -> Region {
+        let (x, y) = (self.x.min(other.x), self.y.min(other.y));
+        Region::new(
+            x, y,
+            self.right().max(other.right()) - x,
+            self.bottom().max(other.bottom()) - y,
+        )
+    }
+
+    pub fn intersection(&self, other: &Region) -> Option<Region> {
+        let (x, y) = (self.x.max(other.x), self.y.max(other.y));
+        let (right, bottom) = (self.right().min(other.right()), self.bottom().min(other.bottom()));
+
+        if x >= right || y >= bottom {
+            return None
+        }
+        Some(Region::new(x, y, right - x, bottom - y))
+    }
+
+    /// Adds the region to a dirty list, growing a touching entry instead of pushing
+    /// where possible and collapsing the list to its bounds once it exceeds the limit
+    pub fn merge_into(self, regions: &mut Vec<Region>, limit: usize) {
+        if let Some(dirty) = regions.iter_mut().find(|dirty| dirty.touches(&self)) {
+            *dirty = dirty.union(&self);
+        } else {
+            regions.push(self);
+        }
+
+        if regions.len() > limit {
+            let bounds = regions
+                .iter()
+                .fold(regions[0], |bounds, dirty| bounds.union(dirty));
+            *regions = vec![bounds];
+        }
+    }
+}
+
+impl FromStr for Region {
+    type Err = String;
+
+    /// Parses `x,y,width,height`
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let parts = value
+            .split(',')
+            .map(|part| part.trim().parse::<u32>())
+            .collect::<Result<Vec<u32>, _>>()
+            .map_err(|e| format!("invalid region {value}: {e}"))?;
+
+        match parts[..] {
+            [x, y, width, height] => Ok(Region::new(x, y, width, height)),
+            _ => Err(format!("invalid region {value}, expected x,y,width,height")),
+        }
+    }
+}
@@ -0,0 +1,341 @@
+#![allow(clippy::excessive_precision)]
+
+use super::prelude::*;
+
+const WHITE: (f32, f32, f32) = (0.95047, 1.0, 1.08883);
+const EPSILON: f32 = 216.0 / 24389.0;
+const KAPPA: f32 = 24389.0 / 27.0;
+
+/// Conversions between srgb and other color spaces, alpha is carried over untouched.
+/// Hue is in degrees, every other component is in the range of its space.
+impl Color {
+    pub fn to_hsv(&self) -> (f32, f32, f32) {
+        let (max, min) = self.extremes();
+        let saturation = if max == 0.0 { 0.0 } else { (max - min) / max };
+        (self.hue(), saturation, max)
+    }
+
+    pub fn from_hsv(h: f32, s: f32, v: f32, a: f32) -> Color {
+        let chroma = v * s;
+        Self::from_hue(h, chroma, v - chroma, a)
+    }
+
+    pub fn to_hsl(&self) -> (f32, f32, f32) {
+        let (max, min) = self.extremes();
+        let lightness = (max + min) / 2.0;
+        let saturation = if max == min {
+            0.0
+        } else {
+            (max - min) / (1.0 - (2.0 * lightness - 1.0).abs())
+        };
+        (self.hue(), saturation, lightness)
+    }
+
+    pub fn from_hsl(h: f32, s: f32, l: f32, a: f32) -> Color {
+        let chroma = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        Self::from_hue(h, chroma, l - chroma / 2.0, a)
+    }
+
+    pub fn to_linear(&self) -> (f32, f32, f32) {
+        (to_linear(self.r), to_linear(self.g), to_linear(self.b))
+    }
+
+    pub fn from_linear(r: f32, g: f32, b: f32, a: f32) -> Color {
+        Color::new(
+            from_linear(r).clamp(0.0, 1.0),
+            from_linear(g).clamp(0.0, 1.0),
+            from_linear(b).clamp(0.0, 1.0),
+            a,
+        )
+    }
+
+    /// CIE Lab under the D65 white point
+    pub fn to_lab(&self) -> (f32, f32, f32) {
+        let (r, g, b) = self.to_linear();
+        let x = 0.4124564 * r + 0.3575761 * g + 0.1804375 * b;
+        let y = 0.2126729 * r + 0.7151522 * g + 0.0721750 * b;
+        let z = 0.0193339 * r + 0.1191920 * g + 0.9503041 * b;
+
+        let (fx, fy, fz) = (
+            lab_f(x / WHITE.0),
+            lab_f(y / WHITE.1),
+            lab_f(z / WHITE.2),
+        );
+        (116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+    }
+
+    pub fn from_lab(l: f32, a: f32, b: f32, alpha: f32) -> Color {
+        let fy = (l + 16.0) / 116.0;
+        let fx = fy + a / 500.0;
+        let fz = fy - b / 200.0;
+
+        let (x, y, z) = (
+            lab_f_inv(fx) * WHITE.0,
+            lab_f_inv(fy) * WHITE.1,
+            lab_f_inv(fz) * WHITE.2,
+        );
+        Self::from_linear(
+            3.2404542 * x - 1.5371385 * y - 0.4985314 * z,
+            -0.9692660 * x + 1.8760108 * y + 0.0415560 * z,
+            0.0556434 * x - 0.2040259 * y + 1.0572252 * z,
+            alpha,
+        )
+    }
+
+    pub fn to_oklab(&self) -> (f32, f32, f32) {
+        let (r, g, b) = self.to_linear();
+        let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+        let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+        let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+
+        (
+            0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+            1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+            0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+        )
+    }
+
+    pub fn from_oklab(l: f32, a: f32, b: f32, alpha: f32) -> Color {
+        let l_ = (l + 0.3963377774 * a + 0.2158037573 * b).powi(3);
+        let m_ = (l - 0.1055613458 * a - 0.0638541728 * b).powi(3);
+        let s_ = (l - 0.0894841775 * a - 1.2914855480 * b).powi(3);
+
+        Self::from_linear(
+            4.0767416621 * l_ - 3.3077115913 * m_ + 0.2309699292 * s_,
+            -1.2684380046 * l_ + 2.6097574011 * m_ - 0.3413193965 * s_,
+            -0.0041960863 * l_ - 0.7034186147 * m_ + 1.7076147010 * s_,
+            alpha,
+        )
+    }
+
+    /// Euclidean distance of the srgb channels, ranges from 0 to sqrt(3)
+    pub fn distance_rgb(&self, other: Color) -> f32 {
+        ((self.r - other.r).powi(2) + (self.g - other.g).powi(2) + (self.b - other.b).powi(2)).sqrt()
+    }
+
+    /// Euclidean distance in oklab, around 0.02 is the just noticeable difference
+    pub fn delta_e_ok(&self, other: Color) -> f32 {
+        let (l1, a1, b1) = self.to_oklab();
+        let (l2, a2, b2) = other.to_oklab();
+        ((l1 - l2).powi(2) + (a1 - a2).powi(2) + (b1 - b2).powi(2)).sqrt()
+    }
+
+    /// CIEDE2000 difference of the lab colors, around 1.0 is the just noticeable difference
+    pub fn delta_e_2000(&self, other: Color) -> f32 {
+        delta_e_2000(self.to_lab(), other.to_lab())
+    }
+
+    fn extremes(&self) -> (f32, f32) {
+        (
+            self.r.max(self.g).max(self.b),
+            self.r.min(self.g).min(self.b),
+        )
+    }
+
+    fn hue(&self) -> f32 {
+        let (max, min) = self.extremes();
+        let delta = max - min;
+
+        let hue = if delta == 0.0 {
+            0.0
+        } else if max == self.r {
+            ((self.g - self.b) / delta).rem_euclid(6.0)
+        } else if max == self.g {
+            (self.b - self.r) / delta + 2.0
+        } else {
+            (self.r - self.g) / delta + 4.0
+        };
+        hue * 60.0
+    }
+
+    fn from_hue(h: f32, chroma: f32, m: f32, a: f32) -> Color {
+        let h = h.rem_euclid(360.0) / 60.0;
+        let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+
+        let (r, g, b) = match h as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        Color::new(r + m, g + m, b + m, a)
+    }
+}
+
+pub fn delta_e_2000(lab1: (f32, f32, f32), lab2: (f32, f32, f32)) -> f32 {
+    let (l1, a1, b1) = lab1;
+    let (l2, a2, b2) = lab2;
+
+    let c_bar = ((a1 * a1 + b1 * b1).sqrt() + (a2 * a2 + b2 * b2).sqrt()) / 2.0;
+    let g = 0.5 * (1.0 - (c_bar.powi(7) / (c_bar.powi(7) + 25f32.powi(7))).sqrt());
+    let (a1, a2) = (a1 * (1.0 + g), a2 * (1.0 + g));
+
+    let (c1, c2) = ((a1 * a1 + b1 * b1).sqrt(), (a2 * a2 + b2 * b2).sqrt());
+    let hue = |a: f32, b: f32| {
+        if a == 0.0 && b == 0.0 { 0.0 } else { b.atan2(a).to_degrees().rem_euclid(360.0) }
+    };
+    let (h1, h2) = (hue(a1, b1), hue(a2, b2));
+
+    let delta_l = l2 - l1;
+    let delta_c = c2 - c1;
+    let delta_h = if c1 * c2 == 0.0 {
+        0.0
+    } else if (h2 - h1).abs() <= 180.0 {
+        h2 - h1
+    } else if h2 - h1 > 180.0 {
+        h2 - h1 - 360.0
+    } else {
+        h2 - h1 + 360.0
+    };
+    let delta_h = 2.0 * (c1 * c2).sqrt() * (delta_h.to_radians() / 2.0).sin();
+
+    let l_bar = (l1 + l2) / 2.0;
+    let c_bar = (c1 + c2) / 2.0;
+    let h_bar = if c1 * c2 == 0.0 {
+        h1 + h2
+    } else if (h1 - h2).abs() <= 180.0 {
+        (h1 + h2) / 2.0
+    } else if h1 + h2 < 360.0 {
+        (h1 + h2 + 360.0) / 2.0
+    } else {
+        (h1 + h2 - 360.0) / 2.0
+    };
+
+    let t = 1.0
+        - 0.17 * (h_bar - 30.0).to_radians().cos()
+        + 0.24 * (2.0 * h_bar).to_radians().cos()
+        + 0.32 * (3.0 * h_bar + 6.0).to_radians().cos()
+        - 0.20 * (4.0 * h_bar - 63.0).to_radians().cos();
+    let delta_theta = 30.0 * (-((h_bar - 275.0) / 25.0).powi(2)).exp();
+    let r_c = 2.0 * (c_bar.powi(7) / (c_bar.powi(7) + 25f32.powi(7))).sqrt();
+    let s_l = 1.0 + 0.015 * (l_bar - 50.0).powi(2) / (20.0 + (l_bar - 50.0).powi(2)).sqrt();
+    let s_c = 1.0 + 0.045 * c_bar;
+    let s_h = 1.0 + 0.015 * c_bar * t;
+    let r_t = -(2.0 * delta_theta).to_radians().sin() * r_c;
+
+    let (l, c, h) = (delta_l / s_l, delta_c / s_c, delta_h / s_h);
+    (l * l + c * c + h * h + r_t * c * h).sqrt()
+}
+
+fn to_linear(channel: f32) -> f32 {
+    if channel <= 0.04045 {
+        channel / 12.92
+    } else {
+        ((channel + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn from_linear(channel: f32) -> f32 {
+    if channel <= 0.0031308 {
+        channel * 12.92
+    } else {
+        1.055 * channel.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn lab_f(t: f32) -> f32 {
+    if t > EPSILON {
+        t.cbrt()
+    } else {
+        (KAPPA * t + 16.0) / 116.0
+    }
+}
+
+fn lab_f_inv(f: f32) -> f32 {
+    if f.powi(3) > EPSILON {
+        f.powi(3)
+    } else {
+        (116.0 * f - 16.0) / KAPPA
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLES: [(u8, u8, u8); 6] = [
+        (255, 0, 0),
+        (12, 200, 90),
+        (30, 60, 250),
+        (128, 128, 128),
+        (250, 240, 10),
+        (0, 0, 0),
+    ];
+
+    fn assert_close(actual: (f32, f32, f32), expected: (f32, f32, f32), tolerance: f32) {
+        let differences = [actual.0 - expected.0, actual.1 - expected.1, actual.2 - expected.2];
+        assert!(
+            differences.iter().all(|difference| difference.abs() <= tolerance),
+            "{actual:?} is not within {tolerance} of {expected:?}"
+        );
+    }
+
+    fn assert_round_trip(convert: impl Fn(Color) -> Color) {
+        for (r, g, b) in SAMPLES {
+            let color = Color::from_rgb(r, g, b);
+            let converted = convert(color);
+            assert_close((converted.r, converted.g, converted.b), (color.r, color.g, color.b), 1e-3);
+        }
+    }
+
+    #[test]
+    fn delta_e_2000_matches_sharma_pairs() {
+        let pair_1 = delta_e_2000((50.0, 2.6772, -79.7751), (50.0, 0.0, -82.7485));
+        let pair_7 = delta_e_2000((50.0, 0.0, 0.0), (50.0, -1.0, 2.0));
+
+        assert!((pair_1 - 2.0425).abs() < 1e-3, "{pair_1}");
+        assert!((pair_7 - 2.3669).abs() < 1e-3, "{pair_7}");
+    }
+
+    #[test]
+    fn red_to_lab() {
+        assert_close(Color::from_rgb(255, 0, 0).to_lab(), (53.2408, 80.0925, 67.2032), 1e-2);
+    }
+
+    #[test]
+    fn red_to_oklab() {
+        assert_close(Color::from_rgb(255, 0, 0).to_oklab(), (0.627955, 0.224863, 0.125846), 1e-4);
+    }
+
+    #[test]
+    fn hsv_round_trip() {
+        assert_round_trip(|color| {
+            let (h, s, v) = color.to_hsv();
+            Color::from_hsv(h, s, v, color.a)
+        });
+    }
+
+    #[test]
+    fn hsl_round_trip() {
+        assert_round_trip(|color| {
+            let (h, s, l) = color.to_hsl();
+            Color::from_hsl(h, s, l, color.a)
+        });
+    }
+
+    #[test]
+    fn linear_round_trip() {
+        assert_round_trip(|color| {
+            let (r, g, b) = color.to_linear();
+            Color::from_linear(r, g, b, color.a)
+        });
+    }
+
+    #[test]
+    fn lab_round_trip() {
+        assert_round_trip(|color| {
+            let (l, a, b) = color.to_lab();
+            Color::from_lab(l, a, b, color.a)
+        });
+    }
+
+    #[test]
+    fn oklab_round_trip() {
+        assert_round_trip(|color| {
+            let (l, a, b) = color.to_oklab();
+            Color::from_oklab(l, a, b, color.a)
+        });
+    }
+}